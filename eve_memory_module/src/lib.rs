@@ -1,7 +1,23 @@
-use spacetimedb::{spacetimedb, ReducerContext, Timestamp};
+use serde::{Deserialize, Serialize};
+use spacetimedb::{spacetimedb, Identity, ReducerContext, SpacetimeType, Timestamp};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// Vector embedding used for semantic search; serialized in JSON as a plain array of numbers
+#[derive(SpacetimeType, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Embedding(pub Vec<f32>);
 
 // Entity Table
+// Indexed by `entity_type` for type-filtered entity queries, by `deleted_at` so live
+// entities (`deleted_at IS NULL`) can be subscribed to efficiently, and by `created_at` so
+// recently-added feeds can subscribe with `WHERE created_at >= ?`. The module API only offers
+// equality lookups on that index, so `get_recent_entities` scans the table instead.
+// `embedding` is stored as a unit vector (see `normalize_embedding`) so similarity search is a
+// plain dot product.
 #[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "entity_type", entity_type))]
+#[spacetimedb(index(btree, name = "deleted_at", deleted_at))]
+#[spacetimedb(index(btree, name = "created_at", created_at))]
 #[derive(Clone)]
 pub struct EveGlobalEntity {
     #[primarykey]
@@ -9,14 +25,49 @@ pub struct EveGlobalEntity {
     #[unique]
     pub name: String,
     pub entity_type: Option<String>,
-    pub data_json: Option<String>, // JSON string for flexible data storage
-    pub embedding_json: Option<String>, // Store embedding as JSON string
+    pub data: Option<HashMap<String, String>>, // Flexible key/value data storage
+    pub embedding: Option<Embedding>,
+    pub owner_id: Identity,
     pub created_at: Timestamp,
     pub updated_at: Timestamp,
+    pub deleted_at: Option<Timestamp>, // Set by soft delete; the row is kept for history
+}
+
+// Entity Type Schema Table
+// Keys that `data` must contain for entities of `entity_type`, checked on create and update
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct EntityTypeSchema {
+    #[primarykey]
+    pub entity_type: String,
+    pub required_keys: Vec<String>,
+    pub created_at: Timestamp,
+}
+
+// Entity Version Table
+// Snapshot of an entity's mutable fields taken just before each update
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "entity_id", entity_id))]
+#[derive(Clone)]
+pub struct EntityVersion {
+    #[primarykey]
+    #[autoinc]
+    pub version_id: u64,
+    pub entity_id: String,
+    pub entity_type: Option<String>,
+    pub data: Option<HashMap<String, String>>,
+    pub embedding: Option<Embedding>,
+    pub snapshot_at: Timestamp,
 }
 
 // Relation Table
+// Both endpoints are indexed so outbound/inbound edge lookups are index scans rather than
+// full table scans; this matters for multi-hop traversals over graphs with millions of edges.
+// `relation_type` is indexed for type-filtered edge queries.
 #[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "source_entity_id", source_entity_id))]
+#[spacetimedb(index(btree, name = "target_entity_id", target_entity_id))]
+#[spacetimedb(index(btree, name = "relation_type", relation_type))]
 #[derive(Clone)]
 pub struct EveGlobalRelation {
     #[primarykey]
@@ -24,123 +75,3510 @@ pub struct EveGlobalRelation {
     pub source_entity_id: String,
     pub target_entity_id: String,
     pub relation_type: String,
+    pub owner_id: Identity,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+}
+
+// Relation Weight Table
+// Optional weight for a relation, kept out of `EveGlobalRelation` so weighted graph algorithms
+// (shortest path, PageRank) don't require migrating the main table. Relations without a row
+// are unweighted.
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct RelationWeight {
+    #[primarykey]
+    pub relation_id: String,
+    pub weight: f64,
+    pub updated_at: Timestamp,
+}
+
+// Relation Label Table
+// Display metadata for a `relation_type`, which is otherwise a machine identifier
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct RelationLabel {
+    #[primarykey]
+    pub relation_type: String,
+    pub display_label: String,
+    pub description: Option<String>,
+    pub color_hex: Option<String>, // "#RRGGBB"
     pub created_at: Timestamp,
 }
 
 // Knowledge Base Table
+// Indexed by source so per-source lookups (Jira, GitHub, etc.) avoid a full table scan.
+// `embedding` is stored as a unit vector, as on `EveGlobalEntity`.
 #[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "source_identifier", source_identifier))]
 #[derive(Clone)]
 pub struct EveGlobalKnowledgeBase {
     #[primarykey]
     pub knowledge_id: String,
     pub text_content: String,
-    pub embedding_json: Option<String>, // Store embedding as JSON string
-    pub tags_json: Option<String>, // Store tags as JSON string
+    pub embedding: Option<Embedding>,
+    pub tags: Vec<String>,
     pub source_identifier: Option<String>,
     pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+    pub deleted_at: Option<Timestamp>, // Set by soft delete; the row is kept as a changelog
 }
 
-// Reducer functions for Entity
-#[spacetimedb(reducer)]
-pub fn create_entity(
-    _ctx: ReducerContext,
-    entity_id: String,
-    name: String,
-    entity_type: Option<String>,
-    data_json: Option<String>,
-    embedding_json: Option<String>,
-) -> () {
-    let now = Timestamp::now();
-    
-    let entity = EveGlobalEntity {
-        entity_id,
-        name,
-        entity_type,
-        data_json,
-        embedding_json,
-        created_at: now,
-        updated_at: now,
-    };
-    
-    let _ = EveGlobalEntity::insert(entity);
+// Knowledge Version Table
+// Snapshot of a knowledge entry's content taken just before each update
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "knowledge_id", knowledge_id))]
+#[derive(Clone)]
+pub struct KnowledgeVersion {
+    #[primarykey]
+    #[autoinc]
+    pub version_id: u64,
+    pub knowledge_id: String,
+    pub text_content: String,
+    pub embedding: Option<Embedding>,
+    pub tags: Vec<String>,
+    pub snapshot_at: Timestamp,
 }
 
-#[spacetimedb(reducer)]
-pub fn update_entity(
-    _ctx: ReducerContext,
-    entity_id: String,
-    entity_type: Option<String>,
-    data_json: Option<String>,
-    embedding_json: Option<String>,
-) -> () {
-    match EveGlobalEntity::filter_by_entity_id(&entity_id) {
-        Some(entity) => {
-            let mut entity_clone = entity.clone();
-            
-            if let Some(entity_type) = entity_type {
-                entity_clone.entity_type = Some(entity_type);
-            }
-            
-            if let Some(data_json) = data_json {
-                entity_clone.data_json = Some(data_json);
-            }
-            
-            if let Some(embedding_json) = embedding_json {
-                entity_clone.embedding_json = Some(embedding_json);
-            }
-            
-            entity_clone.updated_at = Timestamp::now();
-            
-            let _ = EveGlobalEntity::update_by_entity_id(&entity_id, entity_clone);
-        },
-        None => ()
-    }
+// Knowledge Tag Table
+// One row per (tag, knowledge entry) pair, kept in sync with `tags` so clients can
+// subscribe to a single tag, e.g. `SELECT * FROM KnowledgeTag WHERE tag = 'memory'`
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "tag", tag))]
+#[derive(Clone)]
+pub struct KnowledgeTag {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub tag: String,
+    pub knowledge_id: String,
 }
 
-// Reducer functions for Relation
-#[spacetimedb(reducer)]
-pub fn create_relation(
-    _ctx: ReducerContext,
-    relation_id: String,
-    source_entity_id: String,
-    target_entity_id: String,
-    relation_type: String,
-) -> () {
-    // Only create if both entities exist
-    if EveGlobalEntity::filter_by_entity_id(&source_entity_id).is_none() ||
-       EveGlobalEntity::filter_by_entity_id(&target_entity_id).is_none() {
-        return;
-    }
-    
-    let relation = EveGlobalRelation {
-        relation_id,
-        source_entity_id,
-        target_entity_id,
-        relation_type,
-        created_at: Timestamp::now(),
-    };
-    
-    let _ = EveGlobalRelation::insert(relation);
+// Knowledge Source Stats Table
+// Number of live knowledge entries per `source_identifier`; entries without a source are not
+// counted. A row is kept at zero once all of its entries are deleted.
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct KnowledgeSourceStats {
+    #[primarykey]
+    pub source_identifier: String,
+    pub entry_count: u64,
+    pub last_ingested_at: Timestamp,
 }
 
-// Reducer functions for Knowledge Base
-#[spacetimedb(reducer)]
-pub fn create_knowledge(
-    _ctx: ReducerContext,
-    knowledge_id: String,
-    text_content: String,
-    embedding_json: Option<String>,
-    tags_json: Option<String>,
-    source_identifier: Option<String>,
-) -> () {
-    let knowledge = EveGlobalKnowledgeBase {
-        knowledge_id,
-        text_content,
-        embedding_json,
-        tags_json,
-        source_identifier,
-        created_at: Timestamp::now(),
-    };
-    
-    let _ = EveGlobalKnowledgeBase::insert(knowledge);
-} 
\ No newline at end of file
+// Knowledge Cluster Table
+// A named group of knowledge entries, typically produced by client-side clustering
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct KnowledgeCluster {
+    #[primarykey]
+    pub cluster_id: String,
+    pub label: String,
+    pub created_at: Timestamp,
+}
+
+// Knowledge Cluster Membership Table
+// One row per (cluster, knowledge entry) pair; an entry may belong to several clusters
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "cluster_id", cluster_id))]
+#[spacetimedb(index(btree, name = "knowledge_id", knowledge_id))]
+#[derive(Clone)]
+pub struct KnowledgeClusterMembership {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub cluster_id: String,
+    pub knowledge_id: String,
+}
+
+// Knowledge Entity Link Table
+// Associates a knowledge entry with an entity it describes; `link_type` says how, e.g.
+// "about" or "mentions". Each (knowledge, entity, link_type) triple appears at most once.
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "knowledge_id", knowledge_id))]
+#[spacetimedb(index(btree, name = "entity_id", entity_id))]
+#[derive(Clone)]
+pub struct KnowledgeEntityLink {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub knowledge_id: String,
+    pub entity_id: String,
+    pub link_type: String,
+    pub created_at: Timestamp,
+}
+
+// Knowledge Feedback Table
+// Human review of a knowledge entry; `feedback_type` is one of `FEEDBACK_TYPES`
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "knowledge_id", knowledge_id))]
+#[derive(Clone)]
+pub struct KnowledgeFeedback {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub knowledge_id: String,
+    pub feedback_type: String,
+    pub comment: Option<String>,
+    pub submitted_by: Identity,
+    pub submitted_at: Timestamp,
+}
+
+// Knowledge Like Table
+// One row per (knowledge entry, identity) like; the pair is unique, enforced by `like_knowledge`
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "knowledge_id", knowledge_id))]
+#[derive(Clone)]
+pub struct KnowledgeLike {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub knowledge_id: String,
+    pub liked_by: Identity,
+    pub liked_at: Timestamp,
+}
+
+// Knowledge Like Count Table
+// Running like totals, so popular entries can be ranked without counting `KnowledgeLike` rows
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct KnowledgeLikeCount {
+    #[primarykey]
+    pub knowledge_id: String,
+    pub count: i64,
+}
+
+// Knowledge Text Index Table
+// One row per distinct lowercase 3-gram of each live entry's `text_content`, used by
+// `search_knowledge_text`
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "trigram", trigram))]
+#[spacetimedb(index(btree, name = "knowledge_id", knowledge_id))]
+#[derive(Clone)]
+pub struct KnowledgeTextIndex {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub trigram: String,
+    pub knowledge_id: String,
+}
+
+// Entity Text Index Table
+// One row per distinct lowercase 3-gram of each live entity's name and `data` values, used by
+// `search_entities_text`
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "trigram", trigram))]
+#[spacetimedb(index(btree, name = "entity_id", entity_id))]
+#[derive(Clone)]
+pub struct EntityTextIndex {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub trigram: String,
+    pub entity_id: String,
+}
+
+// Pinned Entity Table
+// Per-identity bookmarks; each (entity, identity) pair is unique, enforced by `pin_entity`
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "pinned_by", pinned_by))]
+#[spacetimedb(index(btree, name = "entity_id", entity_id))]
+#[derive(Clone)]
+pub struct PinnedEntity {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub entity_id: String,
+    pub pinned_by: Identity,
+    pub pinned_at: Timestamp,
+}
+
+// Entity Cluster Table
+// A named community of entities, typically produced by graph community detection
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct EntityCluster {
+    #[primarykey]
+    pub cluster_id: String,
+    pub label: String,
+    pub created_at: Timestamp,
+}
+
+// Entity Cluster Membership Table
+// One row per (cluster, entity) pair; an entity may belong to several clusters
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "cluster_id", cluster_id))]
+#[spacetimedb(index(btree, name = "entity_id", entity_id))]
+#[derive(Clone)]
+pub struct EntityClusterMembership {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub cluster_id: String,
+    pub entity_id: String,
+}
+
+// Reasoning Trace Table
+// One row per step of an agent's multi-step reasoning, grouped by `session_id` and ordered by
+// `step_index`, which is unique within a session; a session belongs to the identity that
+// wrote its first step
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "session_id", session_id))]
+#[derive(Clone)]
+pub struct ReasoningTrace {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub session_id: String,
+    pub step_index: u32,
+    pub thought: String,
+    pub action: String,
+    pub observation: String,
+    pub created_at: Timestamp,
+    pub agent_identity: Identity,
+}
+
+// Conversation Message Table
+// Chat history, one row per message; a conversation belongs to the identity that started it
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "conversation_id", conversation_id))]
+#[derive(Clone)]
+pub struct ConversationMessage {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub conversation_id: String,
+    pub role: String, // e.g. "user", "assistant", "system"
+    pub content: String,
+    pub created_at: Timestamp,
+    pub owner_id: Identity,
+}
+
+// Conversation Entity Reference Table
+// Entities a conversation involved, one row per (conversation, entity) pair, so clients can
+// subscribe with `SELECT * FROM ConversationEntityRef WHERE conversation_id = ?`
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "conversation_id", conversation_id))]
+#[spacetimedb(index(btree, name = "entity_id", entity_id))]
+#[derive(Clone)]
+pub struct ConversationEntityRef {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub conversation_id: String,
+    pub entity_id: String,
+    pub first_mentioned_at: Timestamp,
+}
+
+// Knowledge Citation Table
+// A knowledge entry cited by a conversation message; each (message, knowledge) pair at most once
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "message_id", message_id))]
+#[spacetimedb(index(btree, name = "knowledge_id", knowledge_id))]
+#[derive(Clone)]
+pub struct KnowledgeCitation {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub message_id: u64,
+    pub knowledge_id: String,
+    pub cited_at: Timestamp,
+}
+
+// Audit Log Table
+// One row per mutating reducer call; `argument_summary` is a short description, not the payload
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct AuditLog {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub caller: Identity,
+    pub reducer_name: String,
+    pub argument_summary: String,
+    pub called_at: Timestamp,
+}
+
+// Global Config Table
+// Runtime-tunable settings; only keys listed in `CONFIG_KEYS` may be set
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct GlobalConfig {
+    #[primarykey]
+    pub key: String,
+    pub value: String,
+    pub updated_at: Timestamp,
+}
+
+// Notification Table
+// `entity_type` is the kind of record the notification is about ("entity", "knowledge")
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "recipient", recipient))]
+#[derive(Clone)]
+pub struct Notification {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub recipient: Identity,
+    pub message: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub is_read: bool,
+    pub created_at: Timestamp,
+}
+
+// Admin Allowlist Table
+// Identities allowed to call admin-only reducers such as `purge_entity` and `clear_all_knowledge`
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct AdminAllowlist {
+    #[primarykey]
+    pub identity: Identity,
+    pub granted_at: Timestamp,
+}
+
+// Relation Type Count Table
+// Running number of relations per `relation_type`, kept up to date by the relation write paths
+// so `count_relations_by_type` doesn't have to scan every relation
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct RelationTypeCount {
+    #[primarykey]
+    pub relation_type: String,
+    pub count: i64,
+}
+
+// Entity Degree Cache Table
+// Running in/out relation counts per entity, kept up to date by the relation write paths so
+// `top_entities_by_degree` doesn't have to scan every relation
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct EntityDegreeCache {
+    #[primarykey]
+    pub entity_id: String,
+    pub in_degree: i64,
+    pub out_degree: i64,
+    pub updated_at: Timestamp,
+}
+
+// Rate Limit Table
+// One row per caller, counting mutating reducer calls in the caller's current window
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct RateLimit {
+    #[primarykey]
+    pub identity: Identity,
+    pub call_count: u64,
+    pub window_start: Timestamp,
+}
+
+// Scheduled Task Table
+// One-shot maintenance jobs run by `trigger_scheduled_tasks` once `run_at` has passed; a task
+// is deactivated after it runs. `task_type` is one of `TASK_TYPES`.
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct ScheduledTask {
+    #[primarykey]
+    #[autoinc]
+    pub id: u64,
+    pub task_type: String,
+    pub run_at: Timestamp,
+    pub last_run: Option<Timestamp>,
+    pub is_active: bool,
+}
+
+// Graph Stats Singleton Table
+// Running counts behind `get_graph_stats`, kept up to date by the create/delete reducers so
+// reads are O(1). There is a single row, with `singleton_id` always `GRAPH_STATS_ID`.
+#[spacetimedb(table)]
+#[derive(Clone)]
+pub struct GraphStatsSingleton {
+    #[primarykey]
+    pub singleton_id: u8,
+    pub entity_count: i64,
+    pub relation_count: i64,
+    pub knowledge_count: i64,
+    pub deleted_entity_count: i64,
+}
+
+// Config keys accepted by `set_config`; all current settings are non-negative integers
+pub const CONFIG_EMBEDDING_DIMENSION: &str = "embedding_dimension";
+pub const CONFIG_MAX_TAG_COUNT: &str = "max_tag_count";
+const CONFIG_KEYS: &[&str] = &[CONFIG_EMBEDDING_DIMENSION, CONFIG_MAX_TAG_COUNT];
+
+// Mutating reducer calls allowed per identity in each rate limit window
+const RATE_LIMIT_MAX_CALLS: u64 = 100;
+const RATE_LIMIT_WINDOW_SECS: u64 = 60;
+
+// Feedback types accepted by `submit_knowledge_feedback`
+const FEEDBACK_TYPES: &[&str] = &["incorrect", "outdated", "incomplete", "helpful"];
+
+// Task types accepted by `schedule_task`
+pub const TASK_PRUNE_SOFT_DELETED_KNOWLEDGE: &str = "prune_soft_deleted_knowledge";
+pub const TASK_PRUNE_SOFT_DELETED_ENTITIES: &str = "prune_soft_deleted_entities";
+const TASK_TYPES: &[&str] = &[TASK_PRUNE_SOFT_DELETED_KNOWLEDGE, TASK_PRUNE_SOFT_DELETED_ENTITIES];
+
+// How long soft-deleted rows are kept before the prune tasks remove them (30 days)
+const SOFT_DELETE_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+
+// Primary key of the only `GraphStatsSingleton` row
+const GRAPH_STATS_ID: u8 = 0;
+
+// Token that must be passed to `clear_all_knowledge` to confirm the bulk delete
+const CLEAR_KNOWLEDGE_CONFIRM_TOKEN: &str = "CONFIRM_CLEAR";
+
+// Pagination request for reducers that return collections
+#[derive(SpacetimeType, Clone)]
+pub struct PageRequest {
+    pub offset: u64,
+    pub limit: u64,
+}
+
+// One page of a collection; `total` counts every match, not just those in `items`
+#[derive(SpacetimeType, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub has_more: bool,
+}
+
+// Input type for batch entity creation, mirroring the `create_entity` parameters
+#[derive(SpacetimeType, Clone)]
+pub struct EntityInput {
+    pub entity_id: String,
+    pub name: String,
+    pub entity_type: Option<String>,
+    pub data: Option<HashMap<String, String>>,
+    pub embedding: Option<Embedding>,
+}
+
+// Input type for batch relation creation, mirroring the `create_relation` parameters
+#[derive(SpacetimeType, Clone)]
+pub struct RelationInput {
+    pub relation_id: String,
+    pub source_entity_id: String,
+    pub target_entity_id: String,
+    pub relation_type: String,
+}
+
+// Input type for knowledge creation, mirroring the `create_knowledge` parameters; also the
+// shape of each element of the `import_knowledge_from_json` payload
+#[derive(Clone, Deserialize)]
+pub struct KnowledgeInput {
+    pub knowledge_id: String,
+    pub text_content: String,
+    #[serde(default)]
+    pub embedding: Option<Embedding>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub source_identifier: Option<String>,
+}
+
+// Outcome of a batch insert
+#[derive(SpacetimeType, Clone)]
+pub struct BatchResult {
+    pub inserted: usize,
+    pub skipped: usize,
+}
+
+// Outcome of a JSON import; `errors` holds one message per failed entry
+#[derive(SpacetimeType, Clone)]
+pub struct ImportResult {
+    pub inserted: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
+// A single knowledge hit from an embedding search; higher scores are more similar
+#[derive(SpacetimeType, Clone)]
+pub struct KnowledgeSearchResult {
+    pub knowledge_id: String,
+    pub score: f32,
+}
+
+// A single entity hit from an embedding search; higher scores are more similar
+#[derive(SpacetimeType, Clone)]
+pub struct EntitySearchResult {
+    pub entity_id: String,
+    pub name: String,
+    pub score: f32,
+}
+
+// Outcome of merging a duplicate entity into its primary
+#[derive(SpacetimeType, Clone)]
+pub struct MergeResult {
+    pub relations_relinked: usize,
+}
+
+// A relation seen from one endpoint; `direction` is "outbound" or "inbound"
+#[derive(SpacetimeType, Clone)]
+pub struct NeighborResult {
+    pub relation_id: String,
+    pub neighbor_entity_id: String,
+    pub relation_type: String,
+    pub direction: String,
+}
+
+// Aggregate counts for dashboards; `entity_count` and `knowledge_count` cover live rows only
+#[derive(SpacetimeType, Clone)]
+pub struct GraphStats {
+    pub entity_count: u64,
+    pub relation_count: u64,
+    pub knowledge_count: u64,
+    pub deleted_entity_count: u64,
+}
+
+// Number of live rows with a given type, for type distribution charts. Also used for relation
+// types, in which case `entity_type` holds the relation type.
+#[derive(SpacetimeType, Clone)]
+pub struct TypeCount {
+    pub entity_type: String,
+    pub count: u64,
+}
+
+// Number of relations pointing into and out of an entity; a self-loop counts towards both
+#[derive(SpacetimeType, Clone)]
+pub struct EntityDegree {
+    pub entity_id: String,
+    pub in_degree: u64,
+    pub out_degree: u64,
+}
+
+// JSON shape of `export_entity_subgraph`; identities are hex strings and timestamps are
+// microseconds since the Unix epoch
+#[derive(Serialize)]
+pub struct SubgraphExport {
+    pub entities: Vec<ExportedEntity>,
+    pub relations: Vec<ExportedRelation>,
+}
+
+#[derive(Serialize)]
+pub struct ExportedEntity {
+    pub entity_id: String,
+    pub name: String,
+    pub entity_type: Option<String>,
+    pub data: Option<HashMap<String, String>>,
+    pub embedding: Option<Embedding>,
+    pub owner_id: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Serialize)]
+pub struct ExportedRelation {
+    pub relation_id: String,
+    pub source_entity_id: String,
+    pub target_entity_id: String,
+    pub relation_type: String,
+    pub owner_id: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+// The identity that publishes the module becomes the first admin
+#[spacetimedb(init)]
+pub fn init(ctx: ReducerContext) {
+    let _ = GraphStatsSingleton::insert(empty_graph_stats());
+    let _ = AdminAllowlist::insert(AdminAllowlist {
+        identity: ctx.sender,
+        granted_at: Timestamp::now(),
+    });
+}
+
+// Helper functions for the Audit Log
+fn log_audit(ctx: &ReducerContext, reducer_name: &str, summary: &str) {
+    let _ = AuditLog::insert(AuditLog {
+        id: 0, // ID is auto-generated
+        caller: ctx.sender,
+        reducer_name: reducer_name.to_string(),
+        argument_summary: summary.to_string(),
+        called_at: Timestamp::now(),
+    });
+}
+
+// Reducer functions for the Admin Allowlist
+#[spacetimedb(reducer)]
+pub fn grant_admin(
+    ctx: ReducerContext,
+    identity: Identity,
+) -> Result<(), String> {
+    log_audit(&ctx, "grant_admin", &format!("identity={}", identity.to_hex()));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    if AdminAllowlist::filter_by_identity(&identity).is_some() {
+        return Ok(());
+    }
+
+    AdminAllowlist::insert(AdminAllowlist {
+        identity,
+        granted_at: Timestamp::now(),
+    })
+    .map_err(|e| format!("Failed to grant admin: {}", e))?;
+    Ok(())
+}
+
+// The last remaining admin cannot be revoked, so the allowlist never locks everyone out
+#[spacetimedb(reducer)]
+pub fn revoke_admin(
+    ctx: ReducerContext,
+    identity: Identity,
+) -> Result<(), String> {
+    log_audit(&ctx, "revoke_admin", &format!("identity={}", identity.to_hex()));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    if AdminAllowlist::filter_by_identity(&identity).is_none() {
+        return Err(format!("Identity {} is not an admin", identity.to_hex()));
+    }
+    if AdminAllowlist::iter().count() == 1 {
+        return Err("Cannot revoke the last admin".to_string());
+    }
+
+    AdminAllowlist::delete_by_identity(&identity);
+    Ok(())
+}
+
+// Helper functions for the Admin Allowlist
+fn require_admin(ctx: &ReducerContext) -> Result<(), String> {
+    if !is_admin(ctx.sender) {
+        return Err("unauthorized: admin only".to_string());
+    }
+    Ok(())
+}
+
+fn is_admin(identity: Identity) -> bool {
+    AdminAllowlist::filter_by_identity(&identity).is_some()
+}
+
+// Helper functions for Rate Limiting
+// Counts the call against the caller's window, starting a new window once the current one is
+// older than `window_secs`
+fn check_rate_limit(ctx: &ReducerContext, max_calls: u64, window_secs: u64) -> Result<(), String> {
+    let now = Timestamp::now();
+
+    match RateLimit::filter_by_identity(&ctx.sender) {
+        Some(mut limit) => {
+            let elapsed_micros = now
+                .into_micros_since_epoch()
+                .saturating_sub(limit.window_start.into_micros_since_epoch());
+            if elapsed_micros > window_secs.saturating_mul(1_000_000) {
+                limit.call_count = 0;
+                limit.window_start = now;
+            }
+            if limit.call_count >= max_calls {
+                return Err(format!("Rate limit exceeded: at most {} calls per {} seconds", max_calls, window_secs));
+            }
+            limit.call_count += 1;
+            RateLimit::update_by_identity(&ctx.sender, limit);
+        },
+        None => {
+            RateLimit::insert(RateLimit {
+                identity: ctx.sender,
+                call_count: 1,
+                window_start: now,
+            })
+            .map_err(|e| format!("Failed to record rate limit: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+// Reducer functions for Entity
+#[spacetimedb(reducer)]
+pub fn create_entity(
+    ctx: ReducerContext,
+    entity_id: String,
+    name: String,
+    entity_type: Option<String>,
+    data: Option<HashMap<String, String>>,
+    embedding: Option<Embedding>,
+) -> Result<(), String> {
+    log_audit(&ctx, "create_entity", &format!("entity_id={}, name={}", entity_id, name));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    insert_entity(ctx.sender, EntityInput {
+        entity_id,
+        name,
+        entity_type,
+        data,
+        embedding,
+    })?;
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn batch_create_entities(
+    ctx: ReducerContext,
+    entities: Vec<EntityInput>,
+) -> Result<usize, String> {
+    log_audit(&ctx, "batch_create_entities", &format!("{} entities", entities.len()));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    // Duplicate ids or names are skipped rather than aborting the whole batch
+    let mut inserted = 0;
+    for input in entities {
+        if insert_entity(ctx.sender, input).is_ok() {
+            inserted += 1;
+        }
+    }
+    Ok(inserted)
+}
+
+#[spacetimedb(reducer)]
+pub fn update_entity(
+    ctx: ReducerContext,
+    entity_id: String,
+    entity_type: Option<String>,
+    data: Option<HashMap<String, String>>,
+    embedding: Option<Embedding>,
+) -> Result<(), String> {
+    log_audit(&ctx, "update_entity", &format!("entity_id={}", entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    match find_live_entity(&entity_id) {
+        Some(entity) => {
+            if entity.owner_id != ctx.sender {
+                return Err("unauthorized".to_string());
+            }
+
+            let mut entity_clone = entity.clone();
+            
+            if let Some(entity_type) = entity_type {
+                entity_clone.entity_type = Some(entity_type);
+            }
+            
+            if let Some(data) = data {
+                entity_clone.data = Some(data);
+            }
+            
+            if let Some(embedding) = embedding {
+                check_embedding_dimension(&embedding)?;
+                entity_clone.embedding = Some(Embedding(normalize_embedding(embedding.0)));
+            }
+
+            if let Some(entity_type) = &entity_clone.entity_type {
+                validate_entity_data(entity_type, &entity_clone.data)?;
+            }
+            
+            save_entity_update(&entity, entity_clone);
+            Ok(())
+        },
+        None => Err(format!("Entity '{}' not found", entity_id))
+    }
+}
+
+// Brings an entity back to a snapshot; the current state is itself snapshotted first, so a
+// restore can be undone
+#[spacetimedb(reducer)]
+pub fn restore_entity_version(
+    ctx: ReducerContext,
+    version_id: u64,
+) -> Result<(), String> {
+    log_audit(&ctx, "restore_entity_version", &format!("version_id={}", version_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let version = EntityVersion::filter_by_version_id(&version_id)
+        .ok_or_else(|| format!("Entity version {} not found", version_id))?;
+    let entity = find_live_entity(&version.entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", version.entity_id))?;
+    if entity.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+
+    // The snapshot may predate the current schema or embedding dimension, so it is checked
+    // again like any other write
+    if let Some(embedding) = &version.embedding {
+        check_embedding_dimension(embedding)?;
+    }
+    if let Some(entity_type) = &version.entity_type {
+        validate_entity_data(entity_type, &version.data)?;
+    }
+
+    // Unlike update_entity, `None` fields in the snapshot are restored as `None`
+    let mut entity_clone = entity.clone();
+    entity_clone.entity_type = version.entity_type;
+    entity_clone.data = version.data;
+    entity_clone.embedding = version.embedding;
+
+    save_entity_update(&entity, entity_clone);
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn rename_entity(
+    ctx: ReducerContext,
+    entity_id: String,
+    new_name: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "rename_entity", &format!("entity_id={}, new_name={}", entity_id, new_name));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let mut entity = find_live_entity(&entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", entity_id))?;
+    if entity.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+
+    // `name` is unique (soft-deleted entities keep theirs), so make sure no other entity
+    // already holds the new name
+    if let Some(existing) = EveGlobalEntity::filter_by_name(&new_name) {
+        if existing.entity_id != entity_id {
+            return Err(format!("Entity name '{}' is already taken", new_name));
+        }
+    }
+
+    entity.name = new_name;
+    entity.updated_at = Timestamp::now();
+
+    sync_entity_text(&entity);
+    EveGlobalEntity::update_by_entity_id(&entity_id, entity);
+    Ok(())
+}
+
+// The copy is owned by the caller and gets fresh timestamps
+#[spacetimedb(reducer)]
+pub fn duplicate_entity(
+    ctx: ReducerContext,
+    source_entity_id: String,
+    new_entity_id: String,
+    new_name: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "duplicate_entity", &format!("source_entity_id={}, new_entity_id={}", source_entity_id, new_entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let source = find_live_entity(&source_entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", source_entity_id))?;
+
+    insert_entity(ctx.sender, EntityInput {
+        entity_id: new_entity_id,
+        name: new_name,
+        entity_type: source.entity_type,
+        data: source.data,
+        embedding: source.embedding,
+    })?;
+    Ok(())
+}
+
+// Folds `duplicate_id` into `primary_id`: relations, knowledge links, cluster memberships,
+// conversation mentions and pins are re-pointed, data keys missing from the primary are copied
+// over, and the duplicate is soft-deleted
+#[spacetimedb(reducer)]
+pub fn merge_entities(
+    ctx: ReducerContext,
+    primary_id: String,
+    duplicate_id: String,
+) -> Result<MergeResult, String> {
+    log_audit(&ctx, "merge_entities", &format!("primary_id={}, duplicate_id={}", primary_id, duplicate_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if primary_id == duplicate_id {
+        return Err("Cannot merge an entity into itself".to_string());
+    }
+    let primary = find_live_entity(&primary_id)
+        .ok_or_else(|| format!("Entity '{}' not found", primary_id))?;
+    let mut duplicate = find_live_entity(&duplicate_id)
+        .ok_or_else(|| format!("Entity '{}' not found", duplicate_id))?;
+    if primary.owner_id != ctx.sender || duplicate.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+
+    let now = Timestamp::now();
+
+    let relations: Vec<EveGlobalRelation> = EveGlobalRelation::filter_by_source_entity_id(&duplicate_id)
+        .chain(EveGlobalRelation::filter_by_target_entity_id(&duplicate_id))
+        .collect();
+    let mut relinked = HashSet::new();
+    for mut relation in relations {
+        // A self-loop on the duplicate is returned by both indexes; relink it once
+        if !relinked.insert(relation.relation_id.clone()) {
+            continue;
+        }
+        update_degree_cache(&relation.source_entity_id, &relation.target_entity_id, -1);
+        if relation.source_entity_id == duplicate_id {
+            relation.source_entity_id = primary_id.clone();
+        }
+        if relation.target_entity_id == duplicate_id {
+            relation.target_entity_id = primary_id.clone();
+        }
+        relation.updated_at = now;
+        update_degree_cache(&relation.source_entity_id, &relation.target_entity_id, 1);
+        let relation_id = relation.relation_id.clone();
+        EveGlobalRelation::update_by_relation_id(&relation_id, relation);
+    }
+    relink_knowledge_entity_links(&duplicate_id, &primary_id);
+    relink_entity_memberships(&duplicate_id, &primary_id);
+    relink_entity_mentions(&duplicate_id, &primary_id);
+    relink_entity_pins(&duplicate_id, &primary_id);
+
+    let mut merged = primary.clone();
+    if let Some(duplicate_data) = &duplicate.data {
+        let merged_data = merged.data.get_or_insert_with(HashMap::new);
+        for (key, value) in duplicate_data {
+            merged_data.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    save_entity_update(&primary, merged);
+
+    duplicate.deleted_at = Some(now);
+    duplicate.updated_at = now;
+    remove_entity_text(&duplicate_id);
+    EveGlobalEntity::update_by_entity_id(&duplicate_id, duplicate);
+    decrement_stat(GraphStat::Entities, 1);
+    increment_stat(GraphStat::DeletedEntities, 1);
+
+    Ok(MergeResult { relations_relinked: relinked.len() })
+}
+
+// Soft delete: the row and its relations are kept so historical records stay intact
+#[spacetimedb(reducer)]
+pub fn delete_entity(
+    ctx: ReducerContext,
+    entity_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "delete_entity", &format!("entity_id={}", entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let mut entity = find_live_entity(&entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", entity_id))?;
+    if entity.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+
+    let now = Timestamp::now();
+    entity.deleted_at = Some(now);
+    entity.updated_at = now;
+
+    remove_entity_text(&entity_id);
+    EveGlobalEntity::update_by_entity_id(&entity_id, entity);
+    decrement_stat(GraphStat::Entities, 1);
+    increment_stat(GraphStat::DeletedEntities, 1);
+    Ok(())
+}
+
+// Hard delete: removes the row, soft-deleted or not, along with its relations. Admin only,
+// since it erases history other identities may rely on.
+#[spacetimedb(reducer)]
+pub fn purge_entity(
+    ctx: ReducerContext,
+    entity_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "purge_entity", &format!("entity_id={}", entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    let entity = EveGlobalEntity::filter_by_entity_id(&entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", entity_id))?;
+
+    remove_entity_row(&entity);
+    Ok(())
+}
+
+// Hands an entity to another identity. Allowed for the current owner or an admin; existing
+// relations keep their owner.
+#[spacetimedb(reducer)]
+pub fn transfer_entity_ownership(
+    ctx: ReducerContext,
+    entity_id: String,
+    new_owner: Identity,
+) -> Result<(), String> {
+    log_audit(&ctx, "transfer_entity_ownership", &format!("entity_id={}, new_owner={}", entity_id, new_owner.to_hex()));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let mut entity = find_live_entity(&entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", entity_id))?;
+    if entity.owner_id != ctx.sender && !is_admin(ctx.sender) {
+        return Err("unauthorized".to_string());
+    }
+
+    entity.owner_id = new_owner;
+    entity.updated_at = Timestamp::now();
+    EveGlobalEntity::update_by_entity_id(&entity_id, entity);
+    Ok(())
+}
+
+// Permanently deletes soft-deleted entities whose `deleted_at` is more than `older_than_secs`
+// ago, along with their relations. Admin only.
+#[spacetimedb(reducer)]
+pub fn prune_soft_deleted_entities(
+    ctx: ReducerContext,
+    older_than_secs: u64,
+) -> Result<u64, String> {
+    log_audit(&ctx, "prune_soft_deleted_entities", &format!("older_than_secs={}", older_than_secs));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    Ok(prune_entities_deleted_before(older_than_secs))
+}
+
+fn prune_entities_deleted_before(older_than_secs: u64) -> u64 {
+    let now = Timestamp::now();
+    let expired: Vec<EveGlobalEntity> = EveGlobalEntity::iter()
+        .filter(|e| deleted_before(e.deleted_at, older_than_secs, now))
+        .collect();
+    for entity in &expired {
+        remove_entity_row(entity);
+    }
+    expired.len() as u64
+}
+
+// Hard-deletes an entity along with its relations, index rows and cluster memberships, keeping
+// the stats tables in step
+fn remove_entity_row(entity: &EveGlobalEntity) {
+    // Relations are not foreign-key constrained, so remove any that would be left dangling
+    let orphaned: Vec<EveGlobalRelation> = EveGlobalRelation::filter_by_source_entity_id(&entity.entity_id)
+        .chain(EveGlobalRelation::filter_by_target_entity_id(&entity.entity_id))
+        .collect();
+
+    // A self-loop is returned by both indexes but only deleted (and counted) once
+    let mut relations_removed = 0;
+    for relation in orphaned {
+        if EveGlobalRelation::delete_by_relation_id(&relation.relation_id) {
+            relations_removed += 1;
+            update_degree_cache(&relation.source_entity_id, &relation.target_entity_id, -1);
+            adjust_relation_type_count(&relation.relation_type, -1);
+        }
+        RelationWeight::delete_by_relation_id(&relation.relation_id);
+    }
+    decrement_stat(GraphStat::Relations, relations_removed);
+    EntityDegreeCache::delete_by_entity_id(&entity.entity_id);
+
+    remove_entity_text(&entity.entity_id);
+    remove_entity_memberships(&entity.entity_id);
+    remove_knowledge_entity_links(KnowledgeEntityLink::filter_by_entity_id(&entity.entity_id));
+    remove_entity_mentions(&entity.entity_id);
+    remove_entity_pins(&entity.entity_id);
+    EveGlobalEntity::delete_by_entity_id(&entity.entity_id);
+    if entity.deleted_at.is_none() {
+        decrement_stat(GraphStat::Entities, 1);
+    } else {
+        decrement_stat(GraphStat::DeletedEntities, 1);
+    }
+}
+
+fn insert_entity(owner_id: Identity, input: EntityInput) -> Result<EveGlobalEntity, String> {
+    if let Some(embedding) = &input.embedding {
+        check_embedding_dimension(embedding)?;
+    }
+    if let Some(entity_type) = &input.entity_type {
+        validate_entity_data(entity_type, &input.data)?;
+    }
+
+    let entity = build_entity(owner_id, input, Timestamp::now());
+    let entity = EveGlobalEntity::insert(entity)
+        .map_err(|e| format!("Failed to create entity: {}", e))?;
+    sync_entity_text(&entity);
+    increment_stat(GraphStat::Entities, 1);
+    notify_admins(owner_id, &format!("Entity '{}' was created", entity.name), "entity", &entity.entity_id);
+    Ok(entity)
+}
+
+// The row `insert_entity` stores for `input`, once it has passed validation
+fn build_entity(owner_id: Identity, input: EntityInput, now: Timestamp) -> EveGlobalEntity {
+    EveGlobalEntity {
+        entity_id: input.entity_id,
+        name: input.name,
+        entity_type: input.entity_type,
+        data: input.data,
+        embedding: input.embedding.map(|e| Embedding(normalize_embedding(e.0))),
+        owner_id,
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+    }
+}
+
+// Creates or replaces the required `data` keys for an entity type; existing entities are not
+// re-checked until they are next updated. Admin only, since schemas apply to every owner's
+// entities.
+#[spacetimedb(reducer)]
+pub fn upsert_entity_type_schema(
+    ctx: ReducerContext,
+    entity_type: String,
+    required_keys: Vec<String>,
+) -> Result<(), String> {
+    log_audit(&ctx, "upsert_entity_type_schema", &format!("entity_type={}, {} required keys", entity_type, required_keys.len()));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    match EntityTypeSchema::filter_by_entity_type(&entity_type) {
+        Some(mut schema) => {
+            schema.required_keys = required_keys;
+            EntityTypeSchema::update_by_entity_type(&entity_type, schema);
+        },
+        None => {
+            EntityTypeSchema::insert(EntityTypeSchema {
+                entity_type,
+                required_keys,
+                created_at: Timestamp::now(),
+            })
+            .map_err(|e| format!("Failed to create entity type schema: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn list_entity_versions(
+    _ctx: ReducerContext,
+    entity_id: String,
+    page: PageRequest,
+) -> Page<EntityVersion> {
+    let mut versions: Vec<EntityVersion> = EntityVersion::filter_by_entity_id(&entity_id).collect();
+    versions.sort_by_key(|v| v.version_id);
+    paginate(versions, &page)
+}
+
+// Shared write path for updates to an entity's versioned fields: snapshots the old state,
+// bumps `updated_at` and stores the new row
+fn save_entity_update(before: &EveGlobalEntity, mut after: EveGlobalEntity) {
+    snapshot_entity(before);
+    after.updated_at = Timestamp::now();
+    sync_entity_text(&after);
+    EveGlobalEntity::update_by_entity_id(&before.entity_id, after);
+}
+
+fn snapshot_entity(entity: &EveGlobalEntity) {
+    let _ = EntityVersion::insert(EntityVersion {
+        version_id: 0, // ID is auto-generated
+        entity_id: entity.entity_id.clone(),
+        entity_type: entity.entity_type.clone(),
+        data: entity.data.clone(),
+        embedding: entity.embedding.clone(),
+        snapshot_at: Timestamp::now(),
+    });
+}
+
+// Types without a schema accept any data
+fn validate_entity_data(entity_type: &str, data: &Option<HashMap<String, String>>) -> Result<(), String> {
+    let Some(schema) = EntityTypeSchema::filter_by_entity_type(&entity_type.to_string()) else {
+        return Ok(());
+    };
+
+    let missing = missing_required_keys(&schema.required_keys, data);
+    if !missing.is_empty() {
+        return Err(format!("Entity of type '{}' is missing required data keys: {}", entity_type, missing.join(", ")));
+    }
+    Ok(())
+}
+
+fn missing_required_keys<'a>(required_keys: &'a [String], data: &Option<HashMap<String, String>>) -> Vec<&'a str> {
+    required_keys
+        .iter()
+        .filter(|key| !data.as_ref().is_some_and(|d| d.contains_key(*key)))
+        .map(|key| key.as_str())
+        .collect()
+}
+
+// Looks up an entity, treating soft-deleted rows as missing
+fn find_live_entity(entity_id: &String) -> Option<EveGlobalEntity> {
+    EveGlobalEntity::filter_by_entity_id(entity_id).filter(|e| e.deleted_at.is_none())
+}
+
+// Reducer functions for Relation
+#[spacetimedb(reducer)]
+pub fn create_relation(
+    ctx: ReducerContext,
+    relation_id: String,
+    source_entity_id: String,
+    target_entity_id: String,
+    relation_type: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "create_relation", &format!("relation_id={}, type={}", relation_id, relation_type));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    insert_relation(ctx.sender, RelationInput {
+        relation_id,
+        source_entity_id,
+        target_entity_id,
+        relation_type,
+    })?;
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn batch_create_relations(
+    ctx: ReducerContext,
+    relations: Vec<RelationInput>,
+) -> Result<BatchResult, String> {
+    log_audit(&ctx, "batch_create_relations", &format!("{} relations", relations.len()));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    // Relations with a missing or foreign endpoint (or a duplicate id) are skipped, not fatal
+    let mut result = BatchResult { inserted: 0, skipped: 0 };
+    for input in relations {
+        match insert_relation(ctx.sender, input) {
+            Ok(_) => result.inserted += 1,
+            Err(_) => result.skipped += 1,
+        }
+    }
+    Ok(result)
+}
+
+#[spacetimedb(reducer)]
+pub fn update_relation_type(
+    ctx: ReducerContext,
+    relation_id: String,
+    new_relation_type: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "update_relation_type", &format!("relation_id={}, new_relation_type={}", relation_id, new_relation_type));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    match EveGlobalRelation::filter_by_relation_id(&relation_id) {
+        Some(relation) => {
+            if relation.owner_id != ctx.sender {
+                return Err("unauthorized".to_string());
+            }
+
+            adjust_relation_type_count(&relation.relation_type, -1);
+            adjust_relation_type_count(&new_relation_type, 1);
+
+            let mut relation_clone = relation.clone();
+            relation_clone.relation_type = new_relation_type;
+            relation_clone.updated_at = Timestamp::now();
+
+            EveGlobalRelation::update_by_relation_id(&relation_id, relation_clone);
+            Ok(())
+        },
+        None => Err(format!("Relation '{}' not found", relation_id))
+    }
+}
+
+#[spacetimedb(reducer)]
+pub fn delete_relation(
+    ctx: ReducerContext,
+    relation_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "delete_relation", &format!("relation_id={}", relation_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let relation = delete_owned_relation(
+        &relation_id,
+        ctx.sender,
+        EveGlobalRelation::filter_by_relation_id,
+        EveGlobalRelation::delete_by_relation_id,
+    )?;
+    RelationWeight::delete_by_relation_id(&relation_id);
+    decrement_stat(GraphStat::Relations, 1);
+    update_degree_cache(&relation.source_entity_id, &relation.target_entity_id, -1);
+    adjust_relation_type_count(&relation.relation_type, -1);
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn set_relation_weight(
+    ctx: ReducerContext,
+    relation_id: String,
+    weight: f64,
+) -> Result<(), String> {
+    log_audit(&ctx, "set_relation_weight", &format!("relation_id={}, weight={}", relation_id, weight));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let relation = EveGlobalRelation::filter_by_relation_id(&relation_id)
+        .ok_or_else(|| format!("Relation '{}' not found", relation_id))?;
+    if relation.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+    if !weight.is_finite() {
+        return Err("Relation weight must be a finite number".to_string());
+    }
+
+    let relation_weight = RelationWeight {
+        relation_id: relation_id.clone(),
+        weight,
+        updated_at: Timestamp::now(),
+    };
+
+    if RelationWeight::filter_by_relation_id(&relation_id).is_some() {
+        RelationWeight::update_by_relation_id(&relation_id, relation_weight);
+    } else {
+        RelationWeight::insert(relation_weight)
+            .map_err(|e| format!("Failed to set relation weight: {}", e))?;
+    }
+    Ok(())
+}
+
+// Creates or replaces the label for a relation type; `created_at` is kept on replace. Admin
+// only, since labels are shared by every client.
+#[spacetimedb(reducer)]
+pub fn upsert_relation_label(
+    ctx: ReducerContext,
+    relation_type: String,
+    display_label: String,
+    description: Option<String>,
+    color_hex: Option<String>,
+) -> Result<(), String> {
+    log_audit(&ctx, "upsert_relation_label", &format!("relation_type={}, display_label={}", relation_type, display_label));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    if let Some(color) = &color_hex {
+        let is_hex = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if !is_hex {
+            return Err(format!("Invalid color '{}': expected #RRGGBB", color));
+        }
+    }
+
+    match RelationLabel::filter_by_relation_type(&relation_type) {
+        Some(mut label) => {
+            label.display_label = display_label;
+            label.description = description;
+            label.color_hex = color_hex;
+            RelationLabel::update_by_relation_type(&relation_type, label);
+        },
+        None => {
+            RelationLabel::insert(RelationLabel {
+                relation_type,
+                display_label,
+                description,
+                color_hex,
+                created_at: Timestamp::now(),
+            })
+            .map_err(|e| format!("Failed to create relation label: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+// Removes a relation if `caller` owns it and returns the removed row so the derived tables can
+// be updated. The lookup and delete are passed in so the checks can be tested without a host.
+fn delete_owned_relation(
+    relation_id: &String,
+    caller: Identity,
+    find: impl FnOnce(&String) -> Option<EveGlobalRelation>,
+    delete: impl FnOnce(&String) -> bool,
+) -> Result<EveGlobalRelation, String> {
+    let relation = find(relation_id)
+        .ok_or_else(|| format!("Relation '{}' not found", relation_id))?;
+    if relation.owner_id != caller {
+        return Err("unauthorized".to_string());
+    }
+
+    delete(relation_id);
+    Ok(relation)
+}
+
+fn insert_relation(owner_id: Identity, input: RelationInput) -> Result<EveGlobalRelation, String> {
+    // Only create if both entities exist and belong to the caller
+    let source = find_live_entity(&input.source_entity_id)
+        .ok_or_else(|| format!("Source entity '{}' not found", input.source_entity_id))?;
+    let target = find_live_entity(&input.target_entity_id)
+        .ok_or_else(|| format!("Target entity '{}' not found", input.target_entity_id))?;
+    if source.owner_id != owner_id || target.owner_id != owner_id {
+        return Err("unauthorized".to_string());
+    }
+    
+    let now = Timestamp::now();
+
+    let relation = EveGlobalRelation {
+        relation_id: input.relation_id,
+        source_entity_id: input.source_entity_id,
+        target_entity_id: input.target_entity_id,
+        relation_type: input.relation_type,
+        owner_id,
+        created_at: now,
+        updated_at: now,
+    };
+    
+    let relation = EveGlobalRelation::insert(relation)
+        .map_err(|e| format!("Failed to create relation: {}", e))?;
+    increment_stat(GraphStat::Relations, 1);
+    update_degree_cache(&relation.source_entity_id, &relation.target_entity_id, 1);
+    adjust_relation_type_count(&relation.relation_type, 1);
+    Ok(relation)
+}
+
+// Reducer functions for Knowledge Base
+#[spacetimedb(reducer)]
+pub fn create_knowledge(
+    ctx: ReducerContext,
+    knowledge_id: String,
+    text_content: String,
+    embedding: Option<Embedding>,
+    tags: Vec<String>,
+    source_identifier: Option<String>,
+) -> Result<(), String> {
+    log_audit(&ctx, "create_knowledge", &format!("knowledge_id={}", knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    insert_knowledge(ctx.sender, KnowledgeInput {
+        knowledge_id,
+        text_content,
+        embedding,
+        tags,
+        source_identifier,
+    })?;
+    Ok(())
+}
+
+// The payload is a JSON array of `KnowledgeInput` objects; a malformed payload is rejected,
+// but individual entries that fail to insert are reported without aborting the batch
+#[spacetimedb(reducer)]
+pub fn import_knowledge_from_json(
+    ctx: ReducerContext,
+    json_payload: String,
+) -> Result<ImportResult, String> {
+    let inputs: Vec<KnowledgeInput> = serde_json::from_str(&json_payload)
+        .map_err(|e| format!("Invalid import payload: {}", e))?;
+
+    log_audit(&ctx, "import_knowledge_from_json", &format!("{} entries", inputs.len()));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let mut result = ImportResult { inserted: 0, failed: 0, errors: Vec::new() };
+    for input in inputs {
+        let knowledge_id = input.knowledge_id.clone();
+        match insert_knowledge(ctx.sender, input) {
+            Ok(_) => result.inserted += 1,
+            Err(e) => {
+                result.failed += 1;
+                result.errors.push(format!("{}: {}", knowledge_id, e));
+            }
+        }
+    }
+    Ok(result)
+}
+
+// Copies text, tags and source into a new entry; the embedding is left unset because the
+// copy is usually edited and re-embedded
+#[spacetimedb(reducer)]
+pub fn clone_knowledge_entry(
+    ctx: ReducerContext,
+    source_id: String,
+    new_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "clone_knowledge_entry", &format!("source_id={}, new_id={}", source_id, new_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let source = find_live_knowledge(&source_id)
+        .ok_or_else(|| format!("Knowledge '{}' not found", source_id))?;
+
+    insert_knowledge(ctx.sender, KnowledgeInput {
+        knowledge_id: new_id,
+        text_content: source.text_content,
+        embedding: None,
+        tags: source.tags,
+        source_identifier: source.source_identifier,
+    })?;
+    Ok(())
+}
+
+fn insert_knowledge(creator: Identity, input: KnowledgeInput) -> Result<EveGlobalKnowledgeBase, String> {
+    check_tag_count(&input.tags)?;
+    if let Some(embedding) = &input.embedding {
+        check_embedding_dimension(embedding)?;
+    }
+
+    let now = Timestamp::now();
+
+    let knowledge = EveGlobalKnowledgeBase {
+        knowledge_id: input.knowledge_id,
+        text_content: input.text_content,
+        embedding: input.embedding.map(|e| Embedding(normalize_embedding(e.0))),
+        tags: input.tags,
+        source_identifier: input.source_identifier,
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+    };
+    
+    let knowledge = EveGlobalKnowledgeBase::insert(knowledge)
+        .map_err(|e| format!("Failed to create knowledge: {}", e))?;
+    sync_knowledge_tags(&knowledge.knowledge_id, &knowledge.tags);
+    sync_knowledge_text(&knowledge.knowledge_id, &knowledge.text_content);
+    increment_stat(GraphStat::Knowledge, 1);
+    record_source_ingest(&knowledge.source_identifier);
+    notify_admins(creator, "New knowledge was added", "knowledge", &knowledge.knowledge_id);
+    Ok(knowledge)
+}
+
+#[spacetimedb(reducer)]
+pub fn update_knowledge(
+    ctx: ReducerContext,
+    knowledge_id: String,
+    text_content: Option<String>,
+    embedding: Option<Embedding>,
+    tags: Option<Vec<String>>,
+) -> Result<(), String> {
+    log_audit(&ctx, "update_knowledge", &format!("knowledge_id={}", knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    match find_live_knowledge(&knowledge_id) {
+        Some(knowledge) => {
+            snapshot_knowledge(&knowledge);
+            let mut knowledge_clone = knowledge.clone();
+
+            if let Some(text_content) = text_content {
+                knowledge_clone.text_content = text_content;
+            }
+
+            if let Some(embedding) = embedding {
+                check_embedding_dimension(&embedding)?;
+                knowledge_clone.embedding = Some(Embedding(normalize_embedding(embedding.0)));
+            }
+
+            if let Some(tags) = tags {
+                check_tag_count(&tags)?;
+                knowledge_clone.tags = tags;
+            }
+
+            knowledge_clone.updated_at = Timestamp::now();
+
+            sync_knowledge_tags(&knowledge_id, &knowledge_clone.tags);
+            sync_knowledge_text(&knowledge_id, &knowledge_clone.text_content);
+            EveGlobalKnowledgeBase::update_by_knowledge_id(&knowledge_id, knowledge_clone);
+            Ok(())
+        },
+        None => Err(format!("Knowledge '{}' not found", knowledge_id))
+    }
+}
+
+// Soft delete: the row is kept so there is a record of what was removed and when
+#[spacetimedb(reducer)]
+pub fn delete_knowledge(
+    ctx: ReducerContext,
+    knowledge_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "delete_knowledge", &format!("knowledge_id={}", knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let knowledge = soft_delete_knowledge(&knowledge_id, Timestamp::now(), find_live_knowledge)?;
+
+    remove_knowledge_tags(&knowledge_id);
+    remove_knowledge_text(&knowledge_id);
+    record_source_removal(&knowledge.source_identifier);
+    EveGlobalKnowledgeBase::update_by_knowledge_id(&knowledge_id, knowledge);
+    decrement_stat(GraphStat::Knowledge, 1);
+    Ok(())
+}
+
+// Marks a live knowledge entry as deleted and returns the row for the caller to save. The
+// lookup is passed in so the checks can be tested without a host.
+fn soft_delete_knowledge(
+    knowledge_id: &String,
+    now: Timestamp,
+    find_live: impl FnOnce(&String) -> Option<EveGlobalKnowledgeBase>,
+) -> Result<EveGlobalKnowledgeBase, String> {
+    let mut knowledge = find_live(knowledge_id)
+        .ok_or_else(|| "knowledge not found".to_string())?;
+    knowledge.deleted_at = Some(now);
+    knowledge.updated_at = now;
+    Ok(knowledge)
+}
+
+// Hard delete: removes the row whether or not it was soft-deleted first. Admin only, since
+// knowledge has no owner and its feedback, citations and likes go with it; `KnowledgeVersion`
+// snapshots are kept.
+#[spacetimedb(reducer)]
+pub fn purge_knowledge(
+    ctx: ReducerContext,
+    knowledge_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "purge_knowledge", &format!("knowledge_id={}", knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    let knowledge = EveGlobalKnowledgeBase::filter_by_knowledge_id(&knowledge_id)
+        .ok_or_else(|| format!("Knowledge '{}' not found", knowledge_id))?;
+
+    remove_knowledge_row(&knowledge);
+    Ok(())
+}
+
+// Hard-deletes every knowledge entry, live or soft-deleted, for re-index cycles in dev/staging.
+// Version history is kept. Admin only, and `confirm_token` must equal
+// `CLEAR_KNOWLEDGE_CONFIRM_TOKEN`.
+#[spacetimedb(reducer)]
+pub fn clear_all_knowledge(
+    ctx: ReducerContext,
+    confirm_token: String,
+) -> Result<usize, String> {
+    log_audit(&ctx, "clear_all_knowledge", "");
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    if confirm_token != CLEAR_KNOWLEDGE_CONFIRM_TOKEN {
+        return Err("Invalid confirmation token".to_string());
+    }
+
+    let knowledge: Vec<EveGlobalKnowledgeBase> = EveGlobalKnowledgeBase::iter().collect();
+    for entry in &knowledge {
+        remove_knowledge_row(entry);
+    }
+    Ok(knowledge.len())
+}
+
+// Hard-deletes a knowledge row along with its index rows and cluster memberships, keeping the
+// stats tables in step
+fn remove_knowledge_row(knowledge: &EveGlobalKnowledgeBase) {
+    remove_knowledge_tags(&knowledge.knowledge_id);
+    remove_knowledge_text(&knowledge.knowledge_id);
+    remove_knowledge_memberships(&knowledge.knowledge_id);
+    remove_knowledge_entity_links(KnowledgeEntityLink::filter_by_knowledge_id(&knowledge.knowledge_id));
+    remove_knowledge_feedback(&knowledge.knowledge_id);
+    remove_knowledge_citations(&knowledge.knowledge_id);
+    remove_knowledge_likes(&knowledge.knowledge_id);
+    EveGlobalKnowledgeBase::delete_by_knowledge_id(&knowledge.knowledge_id);
+    if knowledge.deleted_at.is_none() {
+        decrement_stat(GraphStat::Knowledge, 1);
+        record_source_removal(&knowledge.source_identifier);
+    }
+}
+
+fn snapshot_knowledge(knowledge: &EveGlobalKnowledgeBase) {
+    let _ = KnowledgeVersion::insert(KnowledgeVersion {
+        version_id: 0, // ID is auto-generated
+        knowledge_id: knowledge.knowledge_id.clone(),
+        text_content: knowledge.text_content.clone(),
+        embedding: knowledge.embedding.clone(),
+        tags: knowledge.tags.clone(),
+        snapshot_at: Timestamp::now(),
+    });
+}
+
+// Looks up a knowledge entry, treating soft-deleted rows as missing
+fn find_live_knowledge(knowledge_id: &String) -> Option<EveGlobalKnowledgeBase> {
+    EveGlobalKnowledgeBase::filter_by_knowledge_id(knowledge_id).filter(|k| k.deleted_at.is_none())
+}
+
+/// Returns every knowledge entry tagged with `tag`, using the tag index rather than
+/// scanning the tags of each row.
+pub fn filter_knowledge_by_tag(tag: String) -> Vec<EveGlobalKnowledgeBase> {
+    KnowledgeTag::filter_by_tag(&tag)
+        .filter_map(|t| find_live_knowledge(&t.knowledge_id))
+        .collect()
+}
+
+/// Returns every knowledge entry ingested from `source_identifier`.
+pub fn filter_knowledge_by_source(source_identifier: String) -> Vec<EveGlobalKnowledgeBase> {
+    EveGlobalKnowledgeBase::filter_by_source_identifier(&Some(source_identifier))
+        .filter(|k| k.deleted_at.is_none())
+        .collect()
+}
+
+#[spacetimedb(reducer)]
+pub fn list_source_stats(_ctx: ReducerContext) -> Vec<KnowledgeSourceStats> {
+    KnowledgeSourceStats::iter().collect()
+}
+
+// Helper functions for Knowledge Source Stats
+fn record_source_ingest(source_identifier: &Option<String>) {
+    let Some(source_identifier) = source_identifier else {
+        return;
+    };
+    let now = Timestamp::now();
+
+    match KnowledgeSourceStats::filter_by_source_identifier(source_identifier) {
+        Some(mut stats) => {
+            stats.entry_count += 1;
+            stats.last_ingested_at = now;
+            KnowledgeSourceStats::update_by_source_identifier(source_identifier, stats);
+        },
+        None => {
+            let _ = KnowledgeSourceStats::insert(KnowledgeSourceStats {
+                source_identifier: source_identifier.clone(),
+                entry_count: 1,
+                last_ingested_at: now,
+            });
+        }
+    }
+}
+
+fn record_source_removal(source_identifier: &Option<String>) {
+    let Some(source_identifier) = source_identifier else {
+        return;
+    };
+
+    if let Some(mut stats) = KnowledgeSourceStats::filter_by_source_identifier(source_identifier) {
+        stats.entry_count = stats.entry_count.saturating_sub(1);
+        KnowledgeSourceStats::update_by_source_identifier(source_identifier, stats);
+    }
+}
+
+// Rebuilds `KnowledgeTag` from the `tags` of every live knowledge entry, for when the index has
+// drifted (e.g. after a bulk import). Returns the number of tag rows written. Admin only.
+#[spacetimedb(reducer)]
+pub fn reindex_knowledge_tags(ctx: ReducerContext) -> Result<u64, String> {
+    log_audit(&ctx, "reindex_knowledge_tags", "");
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+
+    let stale: Vec<u64> = KnowledgeTag::iter().map(|t| t.id).collect();
+    for id in stale {
+        KnowledgeTag::delete_by_id(&id);
+    }
+
+    for knowledge in EveGlobalKnowledgeBase::iter().filter(|k| k.deleted_at.is_none()) {
+        sync_knowledge_tags(&knowledge.knowledge_id, &knowledge.tags);
+    }
+    Ok(KnowledgeTag::iter().count() as u64)
+}
+
+// Helper functions for the Knowledge Tag Index
+fn check_tag_count(tags: &[String]) -> Result<(), String> {
+    match get_config_usize(CONFIG_MAX_TAG_COUNT) {
+        Some(max) if tags.len() > max => {
+            Err(format!("Too many tags: {} (maximum is {})", tags.len(), max))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn sync_knowledge_tags(knowledge_id: &String, tags: &[String]) {
+    remove_knowledge_tags(knowledge_id);
+
+    let mut unique_tags = tags.to_vec();
+    unique_tags.sort();
+    unique_tags.dedup();
+
+    for tag in unique_tags {
+        let _ = KnowledgeTag::insert(KnowledgeTag {
+            id: 0, // ID is auto-generated
+            tag,
+            knowledge_id: knowledge_id.clone(),
+        });
+    }
+}
+
+fn remove_knowledge_tags(knowledge_id: &String) {
+    let stale: Vec<u64> = KnowledgeTag::filter_by_knowledge_id(knowledge_id)
+        .map(|t| t.id)
+        .collect();
+
+    for id in stale {
+        KnowledgeTag::delete_by_id(&id);
+    }
+}
+
+#[spacetimedb(reducer)]
+pub fn search_knowledge_text(
+    _ctx: ReducerContext,
+    query: String,
+    limit: u32,
+) -> Vec<EveGlobalKnowledgeBase> {
+    let mut matches: Vec<EveGlobalKnowledgeBase> = match_trigrams(&query, |trigram| {
+        KnowledgeTextIndex::filter_by_trigram(&trigram.to_string())
+            .map(|t| t.knowledge_id)
+            .collect()
+    })
+    .iter()
+    .filter_map(find_live_knowledge)
+    .collect();
+
+    matches.sort_by_key(|k| Reverse(k.updated_at));
+    matches.truncate(limit as usize);
+    matches
+}
+
+// Helper functions for the Knowledge Text Index
+fn sync_knowledge_text(knowledge_id: &String, text_content: &str) {
+    remove_knowledge_text(knowledge_id);
+
+    for trigram in trigrams(text_content) {
+        let _ = KnowledgeTextIndex::insert(KnowledgeTextIndex {
+            id: 0, // ID is auto-generated
+            trigram,
+            knowledge_id: knowledge_id.clone(),
+        });
+    }
+}
+
+fn remove_knowledge_text(knowledge_id: &String) {
+    let stale: Vec<u64> = KnowledgeTextIndex::filter_by_knowledge_id(knowledge_id)
+        .map(|t| t.id)
+        .collect();
+
+    for id in stale {
+        KnowledgeTextIndex::delete_by_id(&id);
+    }
+}
+
+#[spacetimedb(reducer)]
+pub fn search_entities_text(
+    _ctx: ReducerContext,
+    query: String,
+    limit: u32,
+) -> Vec<EveGlobalEntity> {
+    let mut matches: Vec<EveGlobalEntity> = match_trigrams(&query, |trigram| {
+        EntityTextIndex::filter_by_trigram(&trigram.to_string())
+            .map(|t| t.entity_id)
+            .collect()
+    })
+    .iter()
+    .filter_map(find_live_entity)
+    .collect();
+
+    matches.sort_by_key(|e| Reverse(e.updated_at));
+    matches.truncate(limit as usize);
+    matches
+}
+
+// Helper functions for the Entity Text Index
+// Fields are indexed separately so no trigram spans the boundary between two values
+fn sync_entity_text(entity: &EveGlobalEntity) {
+    remove_entity_text(&entity.entity_id);
+
+    let mut grams = trigrams(&entity.name);
+    if let Some(data) = &entity.data {
+        for value in data.values() {
+            grams.extend(trigrams(value));
+        }
+    }
+    grams.sort();
+    grams.dedup();
+
+    for trigram in grams {
+        let _ = EntityTextIndex::insert(EntityTextIndex {
+            id: 0, // ID is auto-generated
+            trigram,
+            entity_id: entity.entity_id.clone(),
+        });
+    }
+}
+
+fn remove_entity_text(entity_id: &String) {
+    let stale: Vec<u64> = EntityTextIndex::filter_by_entity_id(entity_id)
+        .map(|t| t.id)
+        .collect();
+
+    for id in stale {
+        EntityTextIndex::delete_by_id(&id);
+    }
+}
+
+// Helper functions for Full-Text Search
+// Distinct lowercase character 3-grams of `text`; text shorter than three characters has none
+fn trigrams(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    let mut grams: Vec<String> = chars.windows(3).map(|w| w.iter().collect()).collect();
+    grams.sort();
+    grams.dedup();
+    grams
+}
+
+// Ids whose index rows contain every trigram of `query`; `lookup` returns the ids for one trigram
+fn match_trigrams(query: &str, lookup: impl Fn(&str) -> HashSet<String>) -> Vec<String> {
+    let mut grams = trigrams(query).into_iter();
+    let mut candidates = match grams.next() {
+        Some(first) => lookup(&first),
+        None => return Vec::new(),
+    };
+
+    for gram in grams {
+        if candidates.is_empty() {
+            break;
+        }
+        let ids = lookup(&gram);
+        candidates.retain(|id| ids.contains(id));
+    }
+
+    candidates.into_iter().collect()
+}
+
+// Reducer functions for Knowledge Clusters
+// Creates the cluster on first use; `label` replaces the stored label on later calls, so
+// re-running a clustering pass can rename clusters in place
+#[spacetimedb(reducer)]
+pub fn assign_knowledge_to_cluster(
+    ctx: ReducerContext,
+    cluster_id: String,
+    label: String,
+    knowledge_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "assign_knowledge_to_cluster", &format!("cluster_id={}, knowledge_id={}", cluster_id, knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if find_live_knowledge(&knowledge_id).is_none() {
+        return Err(format!("Knowledge '{}' not found", knowledge_id));
+    }
+
+    match KnowledgeCluster::filter_by_cluster_id(&cluster_id) {
+        Some(mut cluster) => {
+            if cluster.label != label {
+                cluster.label = label;
+                KnowledgeCluster::update_by_cluster_id(&cluster_id, cluster);
+            }
+        },
+        None => {
+            KnowledgeCluster::insert(KnowledgeCluster {
+                cluster_id: cluster_id.clone(),
+                label,
+                created_at: Timestamp::now(),
+            })
+            .map_err(|e| format!("Failed to create cluster: {}", e))?;
+        }
+    }
+
+    // Assigning an entry that is already a member is a no-op
+    if KnowledgeClusterMembership::filter_by_cluster_id(&cluster_id).any(|m| m.knowledge_id == knowledge_id) {
+        return Ok(());
+    }
+
+    KnowledgeClusterMembership::insert(KnowledgeClusterMembership {
+        id: 0, // ID is auto-generated
+        cluster_id,
+        knowledge_id,
+    })
+    .map_err(|e| format!("Failed to assign knowledge to cluster: {}", e))?;
+    Ok(())
+}
+
+// The cluster itself is kept even when its last member is removed
+#[spacetimedb(reducer)]
+pub fn remove_knowledge_from_cluster(
+    ctx: ReducerContext,
+    cluster_id: String,
+    knowledge_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "remove_knowledge_from_cluster", &format!("cluster_id={}, knowledge_id={}", cluster_id, knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let membership = KnowledgeClusterMembership::filter_by_cluster_id(&cluster_id)
+        .find(|m| m.knowledge_id == knowledge_id)
+        .ok_or_else(|| format!("Knowledge '{}' is not in cluster '{}'", knowledge_id, cluster_id))?;
+
+    KnowledgeClusterMembership::delete_by_id(&membership.id);
+    Ok(())
+}
+
+// Helper functions for Knowledge Clusters
+fn remove_knowledge_memberships(knowledge_id: &String) {
+    let stale: Vec<u64> = KnowledgeClusterMembership::filter_by_knowledge_id(knowledge_id)
+        .map(|m| m.id)
+        .collect();
+
+    for id in stale {
+        KnowledgeClusterMembership::delete_by_id(&id);
+    }
+}
+
+// Reducer functions for Knowledge Feedback
+#[spacetimedb(reducer)]
+pub fn submit_knowledge_feedback(
+    ctx: ReducerContext,
+    knowledge_id: String,
+    feedback_type: String,
+    comment: Option<String>,
+) -> Result<(), String> {
+    log_audit(&ctx, "submit_knowledge_feedback", &format!("knowledge_id={}, feedback_type={}", knowledge_id, feedback_type));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if !FEEDBACK_TYPES.contains(&feedback_type.as_str()) {
+        return Err(format!("Unknown feedback type '{}'", feedback_type));
+    }
+    if find_live_knowledge(&knowledge_id).is_none() {
+        return Err(format!("Knowledge '{}' not found", knowledge_id));
+    }
+
+    KnowledgeFeedback::insert(KnowledgeFeedback {
+        id: 0, // ID is auto-generated
+        knowledge_id,
+        feedback_type,
+        comment,
+        submitted_by: ctx.sender,
+        submitted_at: Timestamp::now(),
+    })
+    .map_err(|e| format!("Failed to submit feedback: {}", e))?;
+    Ok(())
+}
+
+// Helper functions for Knowledge Feedback
+fn remove_knowledge_feedback(knowledge_id: &String) {
+    let stale: Vec<u64> = KnowledgeFeedback::filter_by_knowledge_id(knowledge_id)
+        .map(|f| f.id)
+        .collect();
+
+    for id in stale {
+        KnowledgeFeedback::delete_by_id(&id);
+    }
+}
+
+// Reducer functions for Knowledge Likes
+#[spacetimedb(reducer)]
+pub fn like_knowledge(
+    ctx: ReducerContext,
+    knowledge_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "like_knowledge", &format!("knowledge_id={}", knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if find_live_knowledge(&knowledge_id).is_none() {
+        return Err(format!("Knowledge '{}' not found", knowledge_id));
+    }
+    if find_knowledge_like(&knowledge_id, &ctx.sender).is_some() {
+        return Err(format!("Knowledge '{}' is already liked", knowledge_id));
+    }
+
+    KnowledgeLike::insert(KnowledgeLike {
+        id: 0, // ID is auto-generated
+        knowledge_id: knowledge_id.clone(),
+        liked_by: ctx.sender,
+        liked_at: Timestamp::now(),
+    })
+    .map_err(|e| format!("Failed to like knowledge: {}", e))?;
+    adjust_like_count(&knowledge_id, 1);
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn unlike_knowledge(
+    ctx: ReducerContext,
+    knowledge_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "unlike_knowledge", &format!("knowledge_id={}", knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let like = find_knowledge_like(&knowledge_id, &ctx.sender)
+        .ok_or_else(|| format!("Knowledge '{}' is not liked", knowledge_id))?;
+
+    KnowledgeLike::delete_by_id(&like.id);
+    adjust_like_count(&knowledge_id, -1);
+    Ok(())
+}
+
+// Helper functions for Knowledge Likes
+fn find_knowledge_like(knowledge_id: &String, liked_by: &Identity) -> Option<KnowledgeLike> {
+    KnowledgeLike::filter_by_knowledge_id(knowledge_id).find(|l| l.liked_by == *liked_by)
+}
+
+fn adjust_like_count(knowledge_id: &String, delta: i64) {
+    match KnowledgeLikeCount::filter_by_knowledge_id(knowledge_id) {
+        Some(mut like_count) => {
+            like_count.count += delta;
+            KnowledgeLikeCount::update_by_knowledge_id(knowledge_id, like_count);
+        },
+        None => {
+            let _ = KnowledgeLikeCount::insert(KnowledgeLikeCount {
+                knowledge_id: knowledge_id.clone(),
+                count: delta,
+            });
+        }
+    }
+}
+
+fn remove_knowledge_likes(knowledge_id: &String) {
+    let stale: Vec<u64> = KnowledgeLike::filter_by_knowledge_id(knowledge_id)
+        .map(|l| l.id)
+        .collect();
+
+    for id in stale {
+        KnowledgeLike::delete_by_id(&id);
+    }
+    KnowledgeLikeCount::delete_by_knowledge_id(knowledge_id);
+}
+
+// Reducer functions for Knowledge Entity Links
+// Both sides must be live, and only the entity's owner may link knowledge to it
+#[spacetimedb(reducer)]
+pub fn link_knowledge_to_entity(
+    ctx: ReducerContext,
+    knowledge_id: String,
+    entity_id: String,
+    link_type: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "link_knowledge_to_entity", &format!("knowledge_id={}, entity_id={}, link_type={}", knowledge_id, entity_id, link_type));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if find_live_knowledge(&knowledge_id).is_none() {
+        return Err(format!("Knowledge '{}' not found", knowledge_id));
+    }
+    let entity = find_live_entity(&entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", entity_id))?;
+    if entity.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+    if find_knowledge_entity_link(&knowledge_id, &entity_id, &link_type).is_some() {
+        return Err(format!("Knowledge '{}' is already linked to entity '{}' as '{}'", knowledge_id, entity_id, link_type));
+    }
+
+    KnowledgeEntityLink::insert(KnowledgeEntityLink {
+        id: 0, // ID is auto-generated
+        knowledge_id,
+        entity_id,
+        link_type,
+        created_at: Timestamp::now(),
+    })
+    .map_err(|e| format!("Failed to link knowledge to entity: {}", e))?;
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn unlink_knowledge_from_entity(
+    ctx: ReducerContext,
+    knowledge_id: String,
+    entity_id: String,
+    link_type: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "unlink_knowledge_from_entity", &format!("knowledge_id={}, entity_id={}, link_type={}", knowledge_id, entity_id, link_type));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if EveGlobalKnowledgeBase::filter_by_knowledge_id(&knowledge_id).is_none() {
+        return Err(format!("Knowledge '{}' not found", knowledge_id));
+    }
+    let entity = EveGlobalEntity::filter_by_entity_id(&entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", entity_id))?;
+    if entity.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+
+    let link = find_knowledge_entity_link(&knowledge_id, &entity_id, &link_type)
+        .ok_or_else(|| format!("Knowledge '{}' is not linked to entity '{}' as '{}'", knowledge_id, entity_id, link_type))?;
+    KnowledgeEntityLink::delete_by_id(&link.id);
+    Ok(())
+}
+
+// Live knowledge entries linked to the entity, each returned once however many link types join them
+#[spacetimedb(reducer)]
+pub fn find_knowledge_for_entity(
+    _ctx: ReducerContext,
+    entity_id: String,
+) -> Vec<EveGlobalKnowledgeBase> {
+    let mut seen = HashSet::new();
+    KnowledgeEntityLink::filter_by_entity_id(&entity_id)
+        .filter(|l| seen.insert(l.knowledge_id.clone()))
+        .filter_map(|l| find_live_knowledge(&l.knowledge_id))
+        .collect()
+}
+
+// Live entities linked to the knowledge entry, each returned once however many link types join them
+#[spacetimedb(reducer)]
+pub fn find_entities_for_knowledge(
+    _ctx: ReducerContext,
+    knowledge_id: String,
+) -> Vec<EveGlobalEntity> {
+    let mut seen = HashSet::new();
+    KnowledgeEntityLink::filter_by_knowledge_id(&knowledge_id)
+        .filter(|l| seen.insert(l.entity_id.clone()))
+        .filter_map(|l| find_live_entity(&l.entity_id))
+        .collect()
+}
+
+// Helper functions for Knowledge Entity Links
+fn find_knowledge_entity_link(knowledge_id: &String, entity_id: &String, link_type: &String) -> Option<KnowledgeEntityLink> {
+    KnowledgeEntityLink::filter_by_knowledge_id(knowledge_id)
+        .find(|l| l.entity_id == *entity_id && l.link_type == *link_type)
+}
+
+// Moves links from one entity to another; links the target already has are dropped instead
+fn relink_knowledge_entity_links(from_entity_id: &String, to_entity_id: &String) {
+    let links: Vec<KnowledgeEntityLink> = KnowledgeEntityLink::filter_by_entity_id(from_entity_id).collect();
+
+    for mut link in links {
+        if find_knowledge_entity_link(&link.knowledge_id, to_entity_id, &link.link_type).is_some() {
+            KnowledgeEntityLink::delete_by_id(&link.id);
+        } else {
+            let id = link.id;
+            link.entity_id = to_entity_id.clone();
+            KnowledgeEntityLink::update_by_id(&id, link);
+        }
+    }
+}
+
+fn remove_knowledge_entity_links(links: impl Iterator<Item = KnowledgeEntityLink>) {
+    let stale: Vec<u64> = links.map(|l| l.id).collect();
+
+    for id in stale {
+        KnowledgeEntityLink::delete_by_id(&id);
+    }
+}
+
+// Reducer functions for Pinned Entities
+#[spacetimedb(reducer)]
+pub fn pin_entity(
+    ctx: ReducerContext,
+    entity_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "pin_entity", &format!("entity_id={}", entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if find_live_entity(&entity_id).is_none() {
+        return Err(format!("Entity '{}' not found", entity_id));
+    }
+    if find_pin(&entity_id, &ctx.sender).is_some() {
+        return Err(format!("Entity '{}' is already pinned", entity_id));
+    }
+
+    PinnedEntity::insert(PinnedEntity {
+        id: 0, // ID is auto-generated
+        entity_id,
+        pinned_by: ctx.sender,
+        pinned_at: Timestamp::now(),
+    })
+    .map_err(|e| format!("Failed to pin entity: {}", e))?;
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn unpin_entity(
+    ctx: ReducerContext,
+    entity_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "unpin_entity", &format!("entity_id={}", entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let pin = find_pin(&entity_id, &ctx.sender)
+        .ok_or_else(|| format!("Entity '{}' is not pinned", entity_id))?;
+
+    PinnedEntity::delete_by_id(&pin.id);
+    Ok(())
+}
+
+// The caller's pinned entities, oldest pin first; soft-deleted entities are left out
+#[spacetimedb(reducer)]
+pub fn list_pinned_entities(ctx: ReducerContext) -> Vec<EveGlobalEntity> {
+    let mut pins: Vec<PinnedEntity> = PinnedEntity::filter_by_pinned_by(&ctx.sender).collect();
+    pins.sort_by_key(|p| p.id);
+    pins.iter()
+        .filter_map(|p| find_live_entity(&p.entity_id))
+        .collect()
+}
+
+// Helper functions for Pinned Entities
+fn find_pin(entity_id: &String, pinned_by: &Identity) -> Option<PinnedEntity> {
+    PinnedEntity::filter_by_pinned_by(pinned_by).find(|p| p.entity_id == *entity_id)
+}
+
+// Moves pins from one entity to another; identities that already pinned the target keep
+// just that pin
+fn relink_entity_pins(from_entity_id: &String, to_entity_id: &String) {
+    let pins: Vec<PinnedEntity> = PinnedEntity::filter_by_entity_id(from_entity_id).collect();
+
+    for mut pin in pins {
+        if find_pin(to_entity_id, &pin.pinned_by).is_some() {
+            PinnedEntity::delete_by_id(&pin.id);
+        } else {
+            let id = pin.id;
+            pin.entity_id = to_entity_id.clone();
+            PinnedEntity::update_by_id(&id, pin);
+        }
+    }
+}
+
+fn remove_entity_pins(entity_id: &String) {
+    let stale: Vec<u64> = PinnedEntity::filter_by_entity_id(entity_id)
+        .map(|p| p.id)
+        .collect();
+
+    for id in stale {
+        PinnedEntity::delete_by_id(&id);
+    }
+}
+
+// Reducer functions for Entity Clusters
+// Mirrors `assign_knowledge_to_cluster`; only the entity's owner may assign it
+#[spacetimedb(reducer)]
+pub fn assign_entity_to_cluster(
+    ctx: ReducerContext,
+    cluster_id: String,
+    label: String,
+    entity_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "assign_entity_to_cluster", &format!("cluster_id={}, entity_id={}", cluster_id, entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let entity = find_live_entity(&entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", entity_id))?;
+    if entity.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+
+    match EntityCluster::filter_by_cluster_id(&cluster_id) {
+        Some(mut cluster) => {
+            if cluster.label != label {
+                cluster.label = label;
+                EntityCluster::update_by_cluster_id(&cluster_id, cluster);
+            }
+        },
+        None => {
+            EntityCluster::insert(EntityCluster {
+                cluster_id: cluster_id.clone(),
+                label,
+                created_at: Timestamp::now(),
+            })
+            .map_err(|e| format!("Failed to create cluster: {}", e))?;
+        }
+    }
+
+    // Assigning an entity that is already a member is a no-op
+    if EntityClusterMembership::filter_by_cluster_id(&cluster_id).any(|m| m.entity_id == entity_id) {
+        return Ok(());
+    }
+
+    EntityClusterMembership::insert(EntityClusterMembership {
+        id: 0, // ID is auto-generated
+        cluster_id,
+        entity_id,
+    })
+    .map_err(|e| format!("Failed to assign entity to cluster: {}", e))?;
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn remove_entity_from_cluster(
+    ctx: ReducerContext,
+    cluster_id: String,
+    entity_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "remove_entity_from_cluster", &format!("cluster_id={}, entity_id={}", cluster_id, entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let entity = EveGlobalEntity::filter_by_entity_id(&entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", entity_id))?;
+    if entity.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+
+    let membership = EntityClusterMembership::filter_by_cluster_id(&cluster_id)
+        .find(|m| m.entity_id == entity_id)
+        .ok_or_else(|| format!("Entity '{}' is not in cluster '{}'", entity_id, cluster_id))?;
+
+    EntityClusterMembership::delete_by_id(&membership.id);
+    Ok(())
+}
+
+// Helper functions for Entity Clusters
+// Moves memberships from one entity to another; clusters the target is already in are skipped
+fn relink_entity_memberships(from_entity_id: &String, to_entity_id: &String) {
+    let memberships: Vec<EntityClusterMembership> = EntityClusterMembership::filter_by_entity_id(from_entity_id).collect();
+
+    for mut membership in memberships {
+        if EntityClusterMembership::filter_by_cluster_id(&membership.cluster_id).any(|m| m.entity_id == *to_entity_id) {
+            EntityClusterMembership::delete_by_id(&membership.id);
+        } else {
+            let id = membership.id;
+            membership.entity_id = to_entity_id.clone();
+            EntityClusterMembership::update_by_id(&id, membership);
+        }
+    }
+}
+
+fn remove_entity_memberships(entity_id: &String) {
+    let stale: Vec<u64> = EntityClusterMembership::filter_by_entity_id(entity_id)
+        .map(|m| m.id)
+        .collect();
+
+    for id in stale {
+        EntityClusterMembership::delete_by_id(&id);
+    }
+}
+
+// Reducer functions for Graph Queries
+#[spacetimedb(reducer)]
+pub fn find_entity_by_name(
+    _ctx: ReducerContext,
+    name: String,
+) -> Option<EveGlobalEntity> {
+    EveGlobalEntity::filter_by_name(&name).filter(|e| e.deleted_at.is_none())
+}
+
+#[spacetimedb(reducer)]
+pub fn find_entities_by_type(
+    _ctx: ReducerContext,
+    entity_type: String,
+    page: PageRequest,
+) -> Page<EveGlobalEntity> {
+    let entities = EveGlobalEntity::filter_by_entity_type(&Some(entity_type))
+        .filter(|e| e.deleted_at.is_none())
+        .collect();
+    paginate(entities, &page)
+}
+
+// Live entities created at or after `since`, newest first. Scans every entity; clients that
+// need this often should subscribe to the `created_at` range instead.
+#[spacetimedb(reducer)]
+pub fn get_recent_entities(
+    _ctx: ReducerContext,
+    since: Timestamp,
+    limit: u32,
+) -> Vec<EveGlobalEntity> {
+    let mut entities: Vec<EveGlobalEntity> = EveGlobalEntity::iter()
+        .filter(|e| e.deleted_at.is_none() && e.created_at >= since)
+        .collect();
+
+    entities.sort_by_key(|e| Reverse(e.created_at));
+    entities.truncate(limit as usize);
+    entities
+}
+
+#[spacetimedb(reducer)]
+pub fn find_relations_between(
+    _ctx: ReducerContext,
+    entity_a: String,
+    entity_b: String,
+    page: PageRequest,
+) -> Page<EveGlobalRelation> {
+    // Edges are directed, so collect both a -> b and b -> a (a self-loop is returned once)
+    let relations = EveGlobalRelation::filter_by_source_entity_id(&entity_a)
+        .filter(|r| r.target_entity_id == entity_b)
+        .chain(
+            EveGlobalRelation::filter_by_source_entity_id(&entity_b)
+                .filter(|r| r.target_entity_id == entity_a && entity_a != entity_b),
+        )
+        .collect();
+    paginate(relations, &page)
+}
+
+#[spacetimedb(reducer)]
+pub fn find_relations_by_type(
+    _ctx: ReducerContext,
+    relation_type: String,
+    page: PageRequest,
+) -> Page<EveGlobalRelation> {
+    let relations = EveGlobalRelation::filter_by_relation_type(&relation_type).collect();
+    paginate(relations, &page)
+}
+
+#[spacetimedb(reducer)]
+pub fn find_neighbors(
+    _ctx: ReducerContext,
+    entity_id: String,
+    page: PageRequest,
+) -> Page<NeighborResult> {
+    paginate(neighbors_of(&entity_id), &page)
+}
+
+// Breadth-first walk over relations in either direction, up to `max_depth` hops from the root.
+// Soft-deleted entities are not traversed, and only edges between exported entities are kept.
+#[spacetimedb(reducer)]
+pub fn export_entity_subgraph(
+    _ctx: ReducerContext,
+    root_entity_id: String,
+    max_depth: u32,
+) -> Result<String, String> {
+    let root = find_live_entity(&root_entity_id)
+        .ok_or_else(|| format!("Entity '{}' not found", root_entity_id))?;
+
+    let mut visited = HashSet::from([root.entity_id.clone()]);
+    let mut entities = vec![root];
+    let mut queue = VecDeque::from([(root_entity_id, 0u32)]);
+
+    while let Some((entity_id, depth)) = queue.pop_front() {
+        if depth >= max_depth {
+            continue;
+        }
+        for neighbor in neighbors_of(&entity_id) {
+            if visited.contains(&neighbor.neighbor_entity_id) {
+                continue;
+            }
+            if let Some(entity) = find_live_entity(&neighbor.neighbor_entity_id) {
+                visited.insert(entity.entity_id.clone());
+                queue.push_back((entity.entity_id.clone(), depth + 1));
+                entities.push(entity);
+            }
+        }
+    }
+
+    // Outbound edges of every exported entity cover each induced edge exactly once
+    let relations = entities
+        .iter()
+        .flat_map(|e| EveGlobalRelation::filter_by_source_entity_id(&e.entity_id))
+        .filter(|r| visited.contains(&r.target_entity_id))
+        .map(|r| ExportedRelation {
+            relation_id: r.relation_id,
+            source_entity_id: r.source_entity_id,
+            target_entity_id: r.target_entity_id,
+            relation_type: r.relation_type,
+            owner_id: r.owner_id.to_hex(),
+            created_at: r.created_at.into_micros_since_epoch(),
+            updated_at: r.updated_at.into_micros_since_epoch(),
+        })
+        .collect();
+
+    let entities = entities
+        .into_iter()
+        .map(|e| ExportedEntity {
+            entity_id: e.entity_id,
+            name: e.name,
+            entity_type: e.entity_type,
+            data: e.data,
+            embedding: e.embedding,
+            owner_id: e.owner_id.to_hex(),
+            created_at: e.created_at.into_micros_since_epoch(),
+            updated_at: e.updated_at.into_micros_since_epoch(),
+        })
+        .collect();
+
+    serde_json::to_string(&SubgraphExport { entities, relations })
+        .map_err(|e| format!("Failed to serialize subgraph: {}", e))
+}
+
+// Breadth-first search over relations in either direction, so the first path found is a
+// shortest one. Soft-deleted entities are not traversed; `None` means no path within `max_depth`.
+#[spacetimedb(reducer)]
+pub fn find_shortest_path(
+    _ctx: ReducerContext,
+    source_id: String,
+    target_id: String,
+    max_depth: u32,
+) -> Option<Vec<String>> {
+    find_live_entity(&source_id)?;
+    find_live_entity(&target_id)?;
+    if source_id == target_id {
+        return Some(vec![source_id]);
+    }
+
+    // Maps each reached entity to the entity it was first reached from
+    let mut parents: HashMap<String, String> = HashMap::new();
+    let mut visited = HashSet::from([source_id.clone()]);
+    let mut queue = VecDeque::from([(source_id.clone(), 0u32)]);
+
+    while let Some((entity_id, depth)) = queue.pop_front() {
+        if depth >= max_depth {
+            continue;
+        }
+        for neighbor in neighbors_of(&entity_id) {
+            let neighbor_id = neighbor.neighbor_entity_id;
+            if visited.contains(&neighbor_id) || find_live_entity(&neighbor_id).is_none() {
+                continue;
+            }
+            visited.insert(neighbor_id.clone());
+            parents.insert(neighbor_id.clone(), entity_id.clone());
+
+            if neighbor_id == target_id {
+                let mut path = vec![neighbor_id];
+                while let Some(parent) = parents.get(path.last()?) {
+                    path.push(parent.clone());
+                }
+                path.reverse();
+                return Some(path);
+            }
+            queue.push_back((neighbor_id, depth + 1));
+        }
+    }
+    None
+}
+
+#[spacetimedb(reducer)]
+pub fn get_graph_stats(_ctx: ReducerContext) -> GraphStats {
+    let stats = GraphStatsSingleton::filter_by_singleton_id(&GRAPH_STATS_ID)
+        .unwrap_or_else(empty_graph_stats);
+    GraphStats {
+        entity_count: stats.entity_count.max(0) as u64,
+        relation_count: stats.relation_count.max(0) as u64,
+        knowledge_count: stats.knowledge_count.max(0) as u64,
+        deleted_entity_count: stats.deleted_entity_count.max(0) as u64,
+    }
+}
+
+// Live entities per `entity_type`, most common first. Computed on demand because types change
+// on update, restore and merge; untyped entities are not counted.
+#[spacetimedb(reducer)]
+pub fn count_entities_by_type(_ctx: ReducerContext) -> Vec<TypeCount> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for entity in EveGlobalEntity::iter().filter(|e| e.deleted_at.is_none()) {
+        if let Some(entity_type) = entity.entity_type {
+            *counts.entry(entity_type).or_insert(0) += 1;
+        }
+    }
+
+    let mut type_counts: Vec<TypeCount> = counts
+        .into_iter()
+        .map(|(entity_type, count)| TypeCount { entity_type, count })
+        .collect();
+    type_counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.entity_type.cmp(&b.entity_type)));
+    type_counts
+}
+
+#[spacetimedb(reducer)]
+pub fn get_entity_degree(
+    _ctx: ReducerContext,
+    entity_id: String,
+) -> Result<EntityDegree, String> {
+    if find_live_entity(&entity_id).is_none() {
+        return Err(format!("Entity '{}' not found", entity_id));
+    }
+    Ok(degree_of(entity_id))
+}
+
+// Most connected live entities first, by total degree (in + out), read from the degree cache.
+// Entities with no relations have no cache row and are not returned.
+#[spacetimedb(reducer)]
+pub fn top_entities_by_degree(
+    _ctx: ReducerContext,
+    top_n: u32,
+) -> Vec<EntityDegree> {
+    let mut degrees: Vec<EntityDegree> = EntityDegreeCache::iter()
+        .filter(|c| find_live_entity(&c.entity_id).is_some())
+        .map(|c| EntityDegree {
+            entity_id: c.entity_id,
+            in_degree: c.in_degree.max(0) as u64,
+            out_degree: c.out_degree.max(0) as u64,
+        })
+        .collect();
+
+    degrees.sort_by_key(|d| Reverse(d.in_degree + d.out_degree));
+    degrees.truncate(top_n as usize);
+    degrees
+}
+
+// Relations per `relation_type`, most common first, read from the running counts
+#[spacetimedb(reducer)]
+pub fn count_relations_by_type(_ctx: ReducerContext) -> Vec<TypeCount> {
+    let mut type_counts: Vec<TypeCount> = RelationTypeCount::iter()
+        .filter(|c| c.count > 0)
+        .map(|c| TypeCount {
+            entity_type: c.relation_type,
+            count: c.count as u64,
+        })
+        .collect();
+
+    type_counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.entity_type.cmp(&b.entity_type)));
+    type_counts
+}
+
+// Helper functions for Graph Queries
+// Applies `delta` to the source's out-degree and the target's in-degree, creating zeroed rows
+// on first use
+fn update_degree_cache(source_entity_id: &String, target_entity_id: &String, delta: i64) {
+    adjust_degree(source_entity_id, 0, delta);
+    adjust_degree(target_entity_id, delta, 0);
+}
+
+fn adjust_degree(entity_id: &String, in_delta: i64, out_delta: i64) {
+    let now = Timestamp::now();
+
+    match EntityDegreeCache::filter_by_entity_id(entity_id) {
+        Some(mut cache) => {
+            cache.in_degree += in_delta;
+            cache.out_degree += out_delta;
+            cache.updated_at = now;
+            EntityDegreeCache::update_by_entity_id(entity_id, cache);
+        },
+        None => {
+            let _ = EntityDegreeCache::insert(EntityDegreeCache {
+                entity_id: entity_id.clone(),
+                in_degree: in_delta,
+                out_degree: out_delta,
+                updated_at: now,
+            });
+        }
+    }
+}
+
+// Types whose count drops to zero are removed rather than kept as empty rows
+fn adjust_relation_type_count(relation_type: &String, delta: i64) {
+    match RelationTypeCount::filter_by_relation_type(relation_type) {
+        Some(mut type_count) => {
+            type_count.count += delta;
+            if type_count.count <= 0 {
+                RelationTypeCount::delete_by_relation_type(relation_type);
+            } else {
+                RelationTypeCount::update_by_relation_type(relation_type, type_count);
+            }
+        },
+        None => {
+            let _ = RelationTypeCount::insert(RelationTypeCount {
+                relation_type: relation_type.clone(),
+                count: delta,
+            });
+        }
+    }
+}
+
+fn degree_of(entity_id: String) -> EntityDegree {
+    EntityDegree {
+        in_degree: EveGlobalRelation::filter_by_target_entity_id(&entity_id).count() as u64,
+        out_degree: EveGlobalRelation::filter_by_source_entity_id(&entity_id).count() as u64,
+        entity_id,
+    }
+}
+
+fn neighbors_of(entity_id: &String) -> Vec<NeighborResult> {
+    let outbound = EveGlobalRelation::filter_by_source_entity_id(entity_id).map(|r| NeighborResult {
+        relation_id: r.relation_id,
+        neighbor_entity_id: r.target_entity_id,
+        relation_type: r.relation_type,
+        direction: "outbound".to_string(),
+    });
+    let inbound = EveGlobalRelation::filter_by_target_entity_id(entity_id).map(|r| NeighborResult {
+        relation_id: r.relation_id,
+        neighbor_entity_id: r.source_entity_id,
+        relation_type: r.relation_type,
+        direction: "inbound".to_string(),
+    });
+
+    // A self-loop shows up on both indexes; report it once
+    let mut seen = HashSet::new();
+    outbound
+        .chain(inbound)
+        .filter(|n| seen.insert(n.relation_id.clone()))
+        .collect()
+}
+
+// Helper functions for Graph Stats
+// The counters maintained in `GraphStatsSingleton`
+#[derive(Clone, Copy)]
+enum GraphStat {
+    Entities,
+    Relations,
+    Knowledge,
+    DeletedEntities,
+}
+
+fn increment_stat(stat: GraphStat, amount: i64) {
+    adjust_stat(stat, amount);
+}
+
+fn decrement_stat(stat: GraphStat, amount: i64) {
+    adjust_stat(stat, -amount);
+}
+
+fn adjust_stat(stat: GraphStat, delta: i64) {
+    let existing = GraphStatsSingleton::filter_by_singleton_id(&GRAPH_STATS_ID);
+    let is_new = existing.is_none();
+    let mut stats = existing.unwrap_or_else(empty_graph_stats);
+
+    let count = match stat {
+        GraphStat::Entities => &mut stats.entity_count,
+        GraphStat::Relations => &mut stats.relation_count,
+        GraphStat::Knowledge => &mut stats.knowledge_count,
+        GraphStat::DeletedEntities => &mut stats.deleted_entity_count,
+    };
+    *count += delta;
+
+    if is_new {
+        let _ = GraphStatsSingleton::insert(stats);
+    } else {
+        GraphStatsSingleton::update_by_singleton_id(&GRAPH_STATS_ID, stats);
+    }
+}
+
+fn empty_graph_stats() -> GraphStatsSingleton {
+    GraphStatsSingleton {
+        singleton_id: GRAPH_STATS_ID,
+        entity_count: 0,
+        relation_count: 0,
+        knowledge_count: 0,
+        deleted_entity_count: 0,
+    }
+}
+
+// Reducer functions for Similarity Search
+#[spacetimedb(reducer)]
+pub fn search_knowledge_by_embedding(
+    _ctx: ReducerContext,
+    query_embedding: Embedding,
+    page: PageRequest,
+) -> Page<KnowledgeSearchResult> {
+    // Deleted entries, and those without an embedding or with one of a different dimension,
+    // are skipped
+    let query = normalize_embedding(query_embedding.0);
+    let mut results: Vec<KnowledgeSearchResult> = EveGlobalKnowledgeBase::iter()
+        .filter(|k| k.deleted_at.is_none())
+        .filter_map(|k| {
+            let score = dot_product(&query, &k.embedding?.0)?;
+            Some(KnowledgeSearchResult { knowledge_id: k.knowledge_id, score })
+        })
+        .collect();
+
+    // The first page of `limit` results is the top-k
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    paginate(results, &page)
+}
+
+#[spacetimedb(reducer)]
+pub fn search_entities_by_embedding(
+    _ctx: ReducerContext,
+    query_embedding: Embedding,
+    page: PageRequest,
+) -> Page<EntitySearchResult> {
+    // Deleted entities, and those without an embedding or with one of a different dimension,
+    // are skipped
+    let query = normalize_embedding(query_embedding.0);
+    let mut results: Vec<EntitySearchResult> = EveGlobalEntity::iter()
+        .filter(|e| e.deleted_at.is_none())
+        .filter_map(|e| {
+            let score = dot_product(&query, &e.embedding?.0)?;
+            Some(EntitySearchResult { entity_id: e.entity_id, name: e.name, score })
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    paginate(results, &page)
+}
+
+// Helper functions for Similarity Search
+// Vectors of the wrong dimension would make cosine similarity silently meaningless, so stored
+// embeddings must match `embedding_dimension` when it is configured
+fn check_embedding_dimension(embedding: &Embedding) -> Result<(), String> {
+    match get_config_usize(CONFIG_EMBEDDING_DIMENSION) {
+        Some(expected_dim) => validate_embedding(embedding, expected_dim),
+        None => Ok(()),
+    }
+}
+
+fn validate_embedding(embedding: &Embedding, expected_dim: usize) -> Result<(), String> {
+    if embedding.0.len() != expected_dim {
+        return Err(format!(
+            "Embedding has dimension {} (expected {})",
+            embedding.0.len(),
+            expected_dim
+        ));
+    }
+    Ok(())
+}
+
+// Scales `v` to unit length; a zero vector has no direction and is returned as is
+fn normalize_embedding(v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return v;
+    }
+    v.into_iter().map(|x| x / norm).collect()
+}
+
+// Cosine similarity of two unit vectors
+fn dot_product(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+
+    Some(a.iter().zip(b).map(|(x, y)| x * y).sum())
+}
+
+// Reducer functions for Scheduled Tasks
+#[spacetimedb(reducer)]
+pub fn schedule_task(
+    ctx: ReducerContext,
+    task_type: String,
+    run_at: Timestamp,
+) -> Result<(), String> {
+    log_audit(&ctx, "schedule_task", &format!("task_type={}", task_type));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    if !TASK_TYPES.contains(&task_type.as_str()) {
+        return Err(format!("Unknown task type '{}'", task_type));
+    }
+
+    ScheduledTask::insert(ScheduledTask {
+        id: 0, // ID is auto-generated
+        task_type,
+        run_at,
+        last_run: None,
+        is_active: true,
+    })
+    .map_err(|e| format!("Failed to schedule task: {}", e))?;
+    Ok(())
+}
+
+// Runs every active task that is due and returns how many ran. Intended to be called
+// periodically by an external scheduler.
+#[spacetimedb(reducer)]
+pub fn trigger_scheduled_tasks(ctx: ReducerContext) -> Result<u32, String> {
+    log_audit(&ctx, "trigger_scheduled_tasks", "");
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let now = Timestamp::now();
+    let due: Vec<ScheduledTask> = ScheduledTask::iter()
+        .filter(|t| t.is_active && t.run_at.into_micros_since_epoch() <= now.into_micros_since_epoch())
+        .collect();
+
+    let mut run = 0;
+    for mut task in due {
+        run_task(&task.task_type)?;
+        task.last_run = Some(now);
+        task.is_active = false;
+        let id = task.id;
+        ScheduledTask::update_by_id(&id, task);
+        run += 1;
+    }
+    Ok(run)
+}
+
+// Helper functions for Scheduled Tasks
+fn run_task(task_type: &str) -> Result<(), String> {
+    match task_type {
+        TASK_PRUNE_SOFT_DELETED_KNOWLEDGE => {
+            let now = Timestamp::now();
+            let expired: Vec<EveGlobalKnowledgeBase> = EveGlobalKnowledgeBase::iter()
+                .filter(|k| deleted_before(k.deleted_at, SOFT_DELETE_RETENTION_SECS, now))
+                .collect();
+            for knowledge in &expired {
+                remove_knowledge_row(knowledge);
+            }
+            Ok(())
+        }
+        TASK_PRUNE_SOFT_DELETED_ENTITIES => {
+            prune_entities_deleted_before(SOFT_DELETE_RETENTION_SECS);
+            Ok(())
+        }
+        _ => Err(format!("Unknown task type '{}'", task_type)),
+    }
+}
+
+// Whether `deleted_at` is set and more than `older_than_secs` before `now`
+fn deleted_before(deleted_at: Option<Timestamp>, older_than_secs: u64, now: Timestamp) -> bool {
+    let Some(deleted_at) = deleted_at else {
+        return false;
+    };
+    let elapsed_micros = now
+        .into_micros_since_epoch()
+        .saturating_sub(deleted_at.into_micros_since_epoch());
+    elapsed_micros > older_than_secs.saturating_mul(1_000_000)
+}
+
+// Reducer functions for Reasoning Traces
+#[spacetimedb(reducer)]
+pub fn create_reasoning_step(
+    ctx: ReducerContext,
+    session_id: String,
+    step_index: u32,
+    thought: String,
+    action: String,
+    observation: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "create_reasoning_step", &format!("session_id={}, step_index={}", session_id, step_index));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if ReasoningTrace::filter_by_session_id(&session_id).any(|t| t.agent_identity != ctx.sender) {
+        return Err("unauthorized".to_string());
+    }
+    if ReasoningTrace::filter_by_session_id(&session_id).any(|t| t.step_index == step_index) {
+        return Err(format!("Step {} of session '{}' already exists", step_index, session_id));
+    }
+
+    ReasoningTrace::insert(ReasoningTrace {
+        id: 0, // ID is auto-generated
+        session_id,
+        step_index,
+        thought,
+        action,
+        observation,
+        created_at: Timestamp::now(),
+        agent_identity: ctx.sender,
+    })
+    .map_err(|e| format!("Failed to create reasoning step: {}", e))?;
+    Ok(())
+}
+
+// Only the caller's own steps are returned
+#[spacetimedb(reducer)]
+pub fn list_reasoning_trace(
+    ctx: ReducerContext,
+    session_id: String,
+) -> Vec<ReasoningTrace> {
+    let mut steps: Vec<ReasoningTrace> = ReasoningTrace::filter_by_session_id(&session_id)
+        .filter(|t| t.agent_identity == ctx.sender)
+        .collect();
+    steps.sort_by_key(|t| t.step_index);
+    steps
+}
+
+// Reducer functions for Conversations
+#[spacetimedb(reducer)]
+pub fn append_message(
+    ctx: ReducerContext,
+    conversation_id: String,
+    role: String,
+    content: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "append_message", &format!("conversation_id={}, role={}", conversation_id, role));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    if ConversationMessage::filter_by_conversation_id(&conversation_id).any(|m| m.owner_id != ctx.sender) {
+        return Err("unauthorized".to_string());
+    }
+
+    ConversationMessage::insert(ConversationMessage {
+        id: 0, // ID is auto-generated
+        conversation_id,
+        role,
+        content,
+        created_at: Timestamp::now(),
+        owner_id: ctx.sender,
+    })
+    .map_err(|e| format!("Failed to append message: {}", e))?;
+    Ok(())
+}
+
+// Messages in the order they were appended; only the conversation's owner sees them
+#[spacetimedb(reducer)]
+pub fn get_conversation(
+    ctx: ReducerContext,
+    conversation_id: String,
+    page: PageRequest,
+) -> Page<ConversationMessage> {
+    let mut messages: Vec<ConversationMessage> = ConversationMessage::filter_by_conversation_id(&conversation_id)
+        .filter(|m| m.owner_id == ctx.sender)
+        .collect();
+    messages.sort_by_key(|m| m.id);
+    paginate(messages, &page)
+}
+
+// Only the first mention of an entity in a conversation is recorded; later ones are no-ops
+#[spacetimedb(reducer)]
+pub fn record_entity_mention(
+    ctx: ReducerContext,
+    conversation_id: String,
+    entity_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "record_entity_mention", &format!("conversation_id={}, entity_id={}", conversation_id, entity_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    check_conversation_owner(&ctx, &conversation_id)?;
+    if find_live_entity(&entity_id).is_none() {
+        return Err(format!("Entity '{}' not found", entity_id));
+    }
+    if ConversationEntityRef::filter_by_conversation_id(&conversation_id).any(|r| r.entity_id == entity_id) {
+        return Ok(());
+    }
+
+    ConversationEntityRef::insert(ConversationEntityRef {
+        id: 0, // ID is auto-generated
+        conversation_id,
+        entity_id,
+        first_mentioned_at: Timestamp::now(),
+    })
+    .map_err(|e| format!("Failed to record entity mention: {}", e))?;
+    Ok(())
+}
+
+// Only the owner of the message may add citations to it
+#[spacetimedb(reducer)]
+pub fn cite_knowledge(
+    ctx: ReducerContext,
+    message_id: u64,
+    knowledge_id: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "cite_knowledge", &format!("message_id={}, knowledge_id={}", message_id, knowledge_id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let message = ConversationMessage::filter_by_id(&message_id)
+        .ok_or_else(|| format!("Message {} not found", message_id))?;
+    if message.owner_id != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+    if find_live_knowledge(&knowledge_id).is_none() {
+        return Err(format!("Knowledge '{}' not found", knowledge_id));
+    }
+    if KnowledgeCitation::filter_by_message_id(&message_id).any(|c| c.knowledge_id == knowledge_id) {
+        return Err(format!("Message {} already cites knowledge '{}'", message_id, knowledge_id));
+    }
+
+    KnowledgeCitation::insert(KnowledgeCitation {
+        id: 0, // ID is auto-generated
+        message_id,
+        knowledge_id,
+        cited_at: Timestamp::now(),
+    })
+    .map_err(|e| format!("Failed to cite knowledge: {}", e))?;
+    Ok(())
+}
+
+#[spacetimedb(reducer)]
+pub fn get_citations_for_message(
+    _ctx: ReducerContext,
+    message_id: u64,
+) -> Vec<KnowledgeCitation> {
+    KnowledgeCitation::filter_by_message_id(&message_id).collect()
+}
+
+// Helper functions for Conversations
+// A conversation exists once it has a message, and belongs to whoever appended its messages
+fn check_conversation_owner(ctx: &ReducerContext, conversation_id: &String) -> Result<(), String> {
+    let mut messages = ConversationMessage::filter_by_conversation_id(conversation_id).peekable();
+    if messages.peek().is_none() {
+        return Err(format!("Conversation '{}' not found", conversation_id));
+    }
+    if messages.any(|m| m.owner_id != ctx.sender) {
+        return Err("unauthorized".to_string());
+    }
+    Ok(())
+}
+
+fn remove_knowledge_citations(knowledge_id: &String) {
+    let stale: Vec<u64> = KnowledgeCitation::filter_by_knowledge_id(knowledge_id)
+        .map(|c| c.id)
+        .collect();
+
+    for id in stale {
+        KnowledgeCitation::delete_by_id(&id);
+    }
+}
+
+// Moves mentions from one entity to another. Where a conversation mentioned both, the target
+// keeps the earlier `first_mentioned_at`.
+fn relink_entity_mentions(from_entity_id: &String, to_entity_id: &String) {
+    let mentions: Vec<ConversationEntityRef> = ConversationEntityRef::filter_by_entity_id(from_entity_id).collect();
+
+    for mut mention in mentions {
+        let existing = ConversationEntityRef::filter_by_conversation_id(&mention.conversation_id)
+            .find(|r| r.entity_id == *to_entity_id);
+        match existing {
+            Some(mut existing) => {
+                if mention.first_mentioned_at < existing.first_mentioned_at {
+                    let id = existing.id;
+                    existing.first_mentioned_at = mention.first_mentioned_at;
+                    ConversationEntityRef::update_by_id(&id, existing);
+                }
+                ConversationEntityRef::delete_by_id(&mention.id);
+            },
+            None => {
+                let id = mention.id;
+                mention.entity_id = to_entity_id.clone();
+                ConversationEntityRef::update_by_id(&id, mention);
+            }
+        }
+    }
+}
+
+fn remove_entity_mentions(entity_id: &String) {
+    let stale: Vec<u64> = ConversationEntityRef::filter_by_entity_id(entity_id)
+        .map(|r| r.id)
+        .collect();
+
+    for id in stale {
+        ConversationEntityRef::delete_by_id(&id);
+    }
+}
+
+// Reducer functions for Global Config
+// Admin only: the settings apply to every caller's writes
+#[spacetimedb(reducer)]
+pub fn set_config(
+    ctx: ReducerContext,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    log_audit(&ctx, "set_config", &format!("key={}, value={}", key, value));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    require_admin(&ctx)?;
+    if !CONFIG_KEYS.contains(&key.as_str()) {
+        return Err(format!("Unknown config key '{}'", key));
+    }
+    if value.parse::<usize>().is_err() {
+        return Err(format!("Config value for '{}' must be a non-negative integer", key));
+    }
+
+    let config = GlobalConfig {
+        key: key.clone(),
+        value,
+        updated_at: Timestamp::now(),
+    };
+
+    if GlobalConfig::filter_by_key(&key).is_some() {
+        GlobalConfig::update_by_key(&key, config);
+    } else {
+        GlobalConfig::insert(config)
+            .map_err(|e| format!("Failed to set config: {}", e))?;
+    }
+    Ok(())
+}
+
+// Helper functions for Global Config
+pub fn get_config_value(key: String) -> Option<String> {
+    GlobalConfig::filter_by_key(&key).map(|c| c.value)
+}
+
+fn get_config_usize(key: &str) -> Option<usize> {
+    get_config_value(key.to_string())?.parse().ok()
+}
+
+// Reducer functions for Notifications
+#[spacetimedb(reducer)]
+pub fn mark_notification_read(
+    ctx: ReducerContext,
+    id: u64,
+) -> Result<(), String> {
+    log_audit(&ctx, "mark_notification_read", &format!("id={}", id));
+    check_rate_limit(&ctx, RATE_LIMIT_MAX_CALLS, RATE_LIMIT_WINDOW_SECS)?;
+
+    let mut notification = Notification::filter_by_id(&id)
+        .ok_or_else(|| format!("Notification {} not found", id))?;
+    if notification.recipient != ctx.sender {
+        return Err("unauthorized".to_string());
+    }
+
+    notification.is_read = true;
+    Notification::update_by_id(&id, notification);
+    Ok(())
+}
+
+// Helper functions for Notifications
+// New records are announced to every admin except the identity that created them, who
+// already knows
+fn notify_admins(actor: Identity, message: &str, entity_type: &str, entity_id: &str) {
+    let recipients: Vec<Identity> = AdminAllowlist::iter()
+        .map(|a| a.identity)
+        .filter(|identity| *identity != actor)
+        .collect();
+
+    for recipient in recipients {
+        notify(recipient, message, entity_type, entity_id);
+    }
+}
+
+fn notify(recipient: Identity, message: &str, entity_type: &str, entity_id: &str) {
+    let _ = Notification::insert(Notification {
+        id: 0, // ID is auto-generated
+        recipient,
+        message: message.to_string(),
+        entity_type: entity_type.to_string(),
+        entity_id: entity_id.to_string(),
+        is_read: false,
+        created_at: Timestamp::now(),
+    });
+}
+
+// Helper functions for Pagination
+fn paginate<T>(items: Vec<T>, page: &PageRequest) -> Page<T> {
+    let total = items.len() as u64;
+    let has_more = page.offset.saturating_add(page.limit) < total;
+    let items = items
+        .into_iter()
+        .skip(page.offset as usize)
+        .take(page.limit as usize)
+        .collect();
+
+    Page { items, total, has_more }
+}
+
+// Unit tests cover the pure helpers; reducers need a running SpacetimeDB host and are
+// exercised through the module itself
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn sample_data() -> Option<HashMap<String, String>> {
+        Some(HashMap::from([
+            ("typing".to_string(), "dynamic".to_string()),
+            ("paradigm".to_string(), "multi-paradigm".to_string()),
+            ("first_release".to_string(), "1991".to_string()),
+        ]))
+    }
+
+    fn test_identity() -> Identity {
+        Identity::from_byte_array([7; 32])
+    }
+
+    fn sample_entity_input(entity_id: &str, name: &str) -> EntityInput {
+        EntityInput {
+            entity_id: entity_id.to_string(),
+            name: name.to_string(),
+            entity_type: Some("programming_language".to_string()),
+            data: sample_data(),
+            embedding: None,
+        }
+    }
+
+    #[test]
+    fn build_entity_stores_and_returns_multi_key_data() {
+        let now = Timestamp::from_micros_since_epoch(1_000_000);
+        let entity = build_entity(test_identity(), sample_entity_input("python", "Python"), now);
+
+        assert_eq!(entity.data, sample_data());
+        let data = entity.data.as_ref().unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data.get("typing").map(String::as_str), Some("dynamic"));
+        assert_eq!(data.get("first_release").map(String::as_str), Some("1991"));
+        assert_eq!(entity.created_at, now);
+        assert_eq!(entity.deleted_at, None);
+    }
+
+    fn sample_relation_store() -> RefCell<HashMap<String, EveGlobalRelation>> {
+        let now = Timestamp::from_micros_since_epoch(1_000_000);
+        let source = build_entity(test_identity(), sample_entity_input("python", "Python"), now);
+        let target = build_entity(test_identity(), sample_entity_input("rust", "Rust"), now);
+        let relation = EveGlobalRelation {
+            relation_id: "r1".to_string(),
+            source_entity_id: source.entity_id,
+            target_entity_id: target.entity_id,
+            relation_type: "influenced".to_string(),
+            owner_id: test_identity(),
+            created_at: now,
+            updated_at: now,
+        };
+        RefCell::new(HashMap::from([(relation.relation_id.clone(), relation)]))
+    }
+
+    #[test]
+    fn delete_owned_relation_removes_the_relation() {
+        let relations = sample_relation_store();
+        let removed = delete_owned_relation(
+            &"r1".to_string(),
+            test_identity(),
+            |id| relations.borrow().get(id).cloned(),
+            |id| relations.borrow_mut().remove(id).is_some(),
+        )
+        .unwrap();
+
+        assert_eq!(removed.source_entity_id, "python");
+        assert_eq!(removed.target_entity_id, "rust");
+        assert!(relations.borrow().get("r1").is_none());
+    }
+
+    #[test]
+    fn delete_owned_relation_rejects_missing_and_foreign_relations() {
+        let relations = sample_relation_store();
+        let missing = delete_owned_relation(
+            &"r2".to_string(),
+            test_identity(),
+            |id| relations.borrow().get(id).cloned(),
+            |id| relations.borrow_mut().remove(id).is_some(),
+        );
+        assert_eq!(missing.err(), Some("Relation 'r2' not found".to_string()));
+
+        let foreign = delete_owned_relation(
+            &"r1".to_string(),
+            Identity::from_byte_array([8; 32]),
+            |id| relations.borrow().get(id).cloned(),
+            |id| relations.borrow_mut().remove(id).is_some(),
+        );
+        assert_eq!(foreign.err(), Some("unauthorized".to_string()));
+        assert!(relations.borrow().contains_key("r1"));
+    }
+
+    #[test]
+    fn soft_delete_knowledge_removes_the_entry_from_live_reads() {
+        let created = Timestamp::from_micros_since_epoch(1_000_000);
+        let deleted = Timestamp::from_micros_since_epoch(2_000_000);
+        let store = RefCell::new(HashMap::from([(
+            "k1".to_string(),
+            EveGlobalKnowledgeBase {
+                knowledge_id: "k1".to_string(),
+                text_content: "Stale fact".to_string(),
+                embedding: None,
+                tags: vec![],
+                source_identifier: None,
+                created_at: created,
+                updated_at: created,
+                deleted_at: None,
+            },
+        )]));
+        let find_live = |id: &String| {
+            store.borrow().get(id).filter(|k| k.deleted_at.is_none()).cloned()
+        };
+
+        let knowledge = soft_delete_knowledge(&"k1".to_string(), deleted, find_live).unwrap();
+        store.borrow_mut().insert(knowledge.knowledge_id.clone(), knowledge);
+
+        assert!(find_live(&"k1".to_string()).is_none());
+        assert_eq!(store.borrow()["k1"].deleted_at, Some(deleted));
+        assert_eq!(
+            soft_delete_knowledge(&"k1".to_string(), deleted, find_live).err(),
+            Some("knowledge not found".to_string())
+        );
+    }
+
+    #[test]
+    fn paginate_returns_requested_window() {
+        let page: Page<i32> = paginate((1..=5).collect(), &PageRequest { offset: 1, limit: 2 });
+        assert_eq!(page.items, vec![2, 3]);
+        assert_eq!(page.total, 5);
+        assert!(page.has_more);
+    }
+
+    #[test]
+    fn paginate_last_page_has_no_more() {
+        let page: Page<i32> = paginate((1..=5).collect(), &PageRequest { offset: 4, limit: 10 });
+        assert_eq!(page.items, vec![5]);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn paginate_offset_past_end_is_empty() {
+        let page: Page<i32> = paginate(vec![1, 2], &PageRequest { offset: 5, limit: 2 });
+        assert!(page.items.is_empty());
+        assert_eq!(page.total, 2);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn trigrams_are_lowercase_sorted_and_distinct() {
+        assert_eq!(trigrams("Abcabc"), vec!["abc", "bca", "cab"]);
+        assert!(trigrams("ab").is_empty());
+    }
+
+    #[test]
+    fn match_trigrams_requires_every_trigram() {
+        let index: HashMap<&str, Vec<&str>> = HashMap::from([
+            ("hel", vec!["a", "b"]),
+            ("ell", vec!["a", "b"]),
+            ("llo", vec!["a"]),
+        ]);
+        let lookup = |gram: &str| -> HashSet<String> {
+            index.get(gram).into_iter().flatten().map(|id| id.to_string()).collect()
+        };
+
+        assert_eq!(match_trigrams("hello", lookup), vec!["a".to_string()]);
+        let mut ids = match_trigrams("hell", lookup);
+        ids.sort();
+        assert_eq!(ids, vec!["a".to_string(), "b".to_string()]);
+        assert!(match_trigrams("he", lookup).is_empty());
+    }
+
+    #[test]
+    fn exported_entity_keeps_every_data_key() {
+        let entity = ExportedEntity {
+            entity_id: "python".to_string(),
+            name: "Python".to_string(),
+            entity_type: Some("programming_language".to_string()),
+            data: sample_data(),
+            embedding: None,
+            owner_id: String::new(),
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        let json = serde_json::to_value(&entity).unwrap();
+        let data: HashMap<String, String> = serde_json::from_value(json["data"].clone()).unwrap();
+        assert_eq!(Some(data), sample_data());
+    }
+
+    #[test]
+    fn validate_embedding_checks_dimension() {
+        assert!(validate_embedding(&Embedding(vec![0.0; 3]), 3).is_ok());
+        assert!(validate_embedding(&Embedding(vec![0.0; 2]), 3).is_err());
+    }
+
+    #[test]
+    fn normalize_embedding_scales_to_unit_length() {
+        let v = normalize_embedding(vec![3.0, 4.0]);
+        assert!((v[0] - 0.6).abs() < 1e-6);
+        assert!((v[1] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalize_embedding_keeps_zero_vector() {
+        assert_eq!(normalize_embedding(vec![0.0, 0.0]), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn dot_product_of_unit_vectors_is_cosine() {
+        let a = normalize_embedding(vec![1.0, 1.0]);
+        let b = normalize_embedding(vec![1.0, 0.0]);
+        let similarity = dot_product(&a, &b).unwrap();
+        assert!((similarity - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn dot_product_rejects_mismatched_or_empty_vectors() {
+        assert_eq!(dot_product(&[1.0, 2.0], &[1.0]), None);
+        assert_eq!(dot_product(&[], &[]), None);
+    }
+
+    #[test]
+    fn deleted_before_requires_soft_delete_older_than_retention() {
+        let now = Timestamp::from_micros_since_epoch(100_000_000);
+        let deleted_at = Timestamp::from_micros_since_epoch(40_000_000);
+
+        assert!(!deleted_before(None, 0, now));
+        assert!(deleted_before(Some(deleted_at), 59, now));
+        assert!(!deleted_before(Some(deleted_at), 60, now));
+        assert!(!deleted_before(Some(now), 0, now));
+    }
+
+    #[test]
+    fn missing_required_keys_checks_every_key_of_the_data_map() {
+        let required = vec!["typing".to_string(), "paradigm".to_string(), "license".to_string()];
+        assert_eq!(missing_required_keys(&required, &sample_data()), vec!["license"]);
+        assert_eq!(missing_required_keys(&required[..2], &sample_data()), Vec::<&str>::new());
+        assert_eq!(missing_required_keys(&required, &None), vec!["typing", "paradigm", "license"]);
+    }
+}
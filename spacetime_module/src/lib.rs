@@ -1,20 +1,86 @@
 use spacetimedb::{spacetimedb, ReducerContext, Identity, Timestamp, SpacetimeType, Table};
-use log::info; // Make sure log is imported if not already
+use log::{info, warn}; // Make sure log is imported if not already
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+// --- Types ---
+
+#[derive(SpacetimeType, Clone, Debug, PartialEq)]
+pub enum ResourceType {
+    Jira,
+    GitHub,
+    Figma,
+    Generic(String), // Any other source, by name
+}
+
+#[derive(SpacetimeType, Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    Pdf,
+    Docx,
+    Markdown,
+    Html,
+    Json,
+    Custom(String), // Any other format, by name
+}
 
 // --- Tables ---
 
+// Logical tenant boundary: every Resource, Card and Output belongs to exactly one Workspace,
+// and rows may only be linked to rows in the same Workspace.
+#[spacetimedb(table)]
+pub struct Workspace {
+    #[primarykey]
+    #[autoinc]
+    id: u64,
+    name: String,
+    created_at: Timestamp,
+    owner_id: Identity,
+}
+
+// Roles a WorkspaceMember may hold. Editors can create and change rows in the Workspace;
+// viewers can only read them.
+const EDITOR_ROLE: &str = "editor";
+const VIEWER_ROLE: &str = "viewer";
+const WORKSPACE_ROLES: [&str; 2] = [EDITOR_ROLE, VIEWER_ROLE];
+
+// Identities other than the owner with access to a Workspace. Each (workspace_id, member_id)
+// pair appears at most once; this is enforced by the reducers because a composite primary key
+// is not available.
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "workspace_id", workspace_id))]
+#[spacetimedb(index(btree, name = "member_id", member_id))]
+pub struct WorkspaceMember {
+    #[primarykey]
+    #[autoinc]
+    id: u64,
+    workspace_id: u64,
+    member_id: Identity,
+    role: String, // One of WORKSPACE_ROLES
+    joined_at: Timestamp,
+}
+
+// Clients subscribe per user with `SELECT * FROM Resource WHERE owner_id = ?`; the owner_id
+// index keeps that from being a full scan.
 #[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "resource_type", resource_type))]
+#[spacetimedb(index(btree, name = "owner_id", owner_id))]
+#[spacetimedb(index(btree, name = "workspace_id", workspace_id))]
 pub struct Resource {
     #[primarykey]
     #[autoinc] // Add autoinc for automatic ID generation
     id: u64,
     name: String,
-    resource_type: String, // e.g., "Jira", "GitHub", "Figma"
+    resource_type: ResourceType,
     ingested_at: Timestamp,
+    updated_at: Timestamp,
     owner_id: Identity,
+    workspace_id: u64,
 }
 
+// Clients subscribe per user with `SELECT * FROM Card WHERE owner_id = ?` (index-backed).
 #[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "owner_id", owner_id))]
+#[spacetimedb(index(btree, name = "workspace_id", workspace_id))]
 pub struct Card {
     #[primarykey]
     #[autoinc]
@@ -22,22 +88,174 @@ pub struct Card {
     title: String,
     content: String, // Or potentially a more complex type later
     created_at: Timestamp,
+    updated_at: Timestamp,
+    owner_id: Identity,
+    workspace_id: u64,
+    // Relationships live in the CardResource and CardLink tables
+}
+
+// One row per distinct lowercase 3-gram of each Card's title and content, used by `search_cards`.
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "trigram", trigram))]
+#[spacetimedb(index(btree, name = "card_id", card_id))]
+pub struct CardTextIndex {
+    #[primarykey]
+    #[autoinc]
+    id: u64,
+    trigram: String,
+    card_id: u64,
+}
+
+// Access to a single Card granted by its owner; `can_edit` additionally allows `update_card`.
+// Each (card_id, shared_with) pair appears at most once, enforced by `share_card`.
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "card_id", card_id))]
+#[spacetimedb(index(btree, name = "shared_with", shared_with))]
+pub struct SharedCard {
+    #[primarykey]
+    #[autoinc]
+    id: u64,
+    card_id: u64,
+    shared_with: Identity,
+    can_edit: bool,
+    shared_at: Timestamp,
+}
+
+#[spacetimedb(table)]
+pub struct CardVersion {
+    #[primarykey]
+    #[autoinc]
+    version_id: u64,
+    card_id: u64,
+    title: String, // Title and content as they were before the update
+    content: String,
+    snapshot_at: Timestamp,
     owner_id: Identity,
-    // TODO: Add relationships to Resources, other Cards, etc. later
 }
 
 #[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "workspace_id", workspace_id))]
 pub struct Output {
     #[primarykey]
     #[autoinc]
     id: u64,
     name: String,
-    format: String, // e.g., "PDF", "DocX"
+    format: OutputFormat,
     created_at: Timestamp,
+    updated_at: Timestamp,
     source_card_id: Option<u64>, // Link back to the card it came from
     owner_id: Identity,
+    workspace_id: u64,
 }
 
+// Generated payload of an Output, kept apart from the metadata so subscribing to Output stays
+// cheap even when payloads are large.
+#[spacetimedb(table)]
+pub struct OutputContent {
+    #[primarykey]
+    output_id: u64,
+    content_bytes: Vec<u8>,
+    stored_at: Timestamp,
+}
+
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "tag", tag))]
+#[spacetimedb(index(btree, name = "card_id", card_id))]
+pub struct CardTag {
+    #[primarykey]
+    #[autoinc]
+    id: u64,
+    card_id: u64,
+    tag: String,
+    tagged_by: Identity,
+    tagged_at: Timestamp,
+}
+
+#[spacetimedb(table)]
+#[spacetimedb(index(btree, name = "tag", tag))]
+#[spacetimedb(index(btree, name = "resource_id", resource_id))]
+pub struct ResourceTag {
+    #[primarykey]
+    #[autoinc]
+    id: u64,
+    resource_id: u64,
+    tag: String,
+    tagged_by: Identity,
+    tagged_at: Timestamp,
+}
+
+#[spacetimedb(table)]
+pub struct CardResource {
+    #[primarykey]
+    #[autoinc]
+    id: u64,
+    card_id: u64,
+    resource_id: u64,
+    linked_at: Timestamp,
+    owner_id: Identity,
+}
+
+#[spacetimedb(table)]
+pub struct CardLink {
+    #[primarykey]
+    #[autoinc]
+    id: u64,
+    source_card_id: u64,
+    target_card_id: u64,
+    link_type: String, // e.g., "parent", "related"
+    created_at: Timestamp,
+    owner_id: Identity,
+}
+
+#[spacetimedb(table)]
+pub struct UserProfile {
+    #[primarykey]
+    identity: Identity,
+    display_name: String,
+    avatar_url: Option<String>,
+    created_at: Timestamp,
+    updated_at: Timestamp,
+}
+
+#[spacetimedb(table)]
+pub struct Session {
+    #[primarykey]
+    #[autoinc]
+    session_id: u64,
+    identity: Identity,
+    connected_at: Timestamp,
+    disconnected_at: Option<Timestamp>, // None while the session is still open
+}
+
+// --- Reducer Result Types ---
+
+#[derive(SpacetimeType)]
+pub struct PageRequest {
+    offset: u64,
+    limit: u64,
+}
+
+#[derive(SpacetimeType)]
+pub struct Page<T> {
+    items: Vec<T>,
+    total: u64, // Every match, not just those in `items`
+    has_more: bool,
+}
+
+#[derive(SpacetimeType)]
+pub struct DeleteResult {
+    card_deleted: bool,
+    links_removed: u64, // CardLink and CardResource rows removed alongside the card
+    outputs_detached: u64, // Outputs whose source_card_id was cleared
+}
+
+#[derive(SpacetimeType)]
+pub struct ResourceDeleteResult {
+    resource_deleted: bool,
+    outputs_detached: u64, // Outputs whose source_card_id was cleared
+    outputs_deleted: u64, // Outputs removed when cascading
+    card_links_removed: u64,
+}
 
 // --- Lifecycle Reducers ---
 
@@ -48,47 +266,890 @@ pub fn init(_ctx: ReducerContext, _timestamp: Timestamp) {
 }
 
 #[spacetimedb(reducer)]
-pub fn client_connected(ctx: ReducerContext, _timestamp: Timestamp, _identity: Identity) {
+pub fn client_connected(ctx: ReducerContext, timestamp: Timestamp, _identity: Identity) {
     // Called every time a new client connects
     info!("Client connected: {:?}", ctx.sender);
+    if let Err(e) = Session::insert(Session {
+        session_id: 0, // ID is auto-generated
+        identity: ctx.sender,
+        connected_at: timestamp,
+        disconnected_at: None,
+    }) {
+        warn!("Failed to record session for {:?}: {:?}", ctx.sender, e);
+    }
 }
 
 #[spacetimedb(reducer)]
-pub fn client_disconnected(ctx: ReducerContext, _timestamp: Timestamp, _identity: Identity) {
+pub fn client_disconnected(ctx: ReducerContext, timestamp: Timestamp, _identity: Identity) {
     // Called every time a client disconnects
     info!("Client disconnected: {:?}", ctx.sender);
+    // An identity may hold several connections; close the most recently opened one
+    let open_session = Session::filter_by_identity(&ctx.sender)
+        .filter(|session| session.disconnected_at.is_none())
+        .max_by_key(|session| session.connected_at);
+    match open_session {
+        Some(mut session) => {
+            let session_id = session.session_id;
+            session.disconnected_at = Some(timestamp);
+            Session::update_by_session_id(&session_id, session);
+        }
+        None => warn!("No open session found for {:?}", ctx.sender),
+    }
 }
 
 // --- COSine Reducers ---
 
-/// Creates a new Resource associated with the calling identity.
+/// Creates or updates the profile of the calling identity.
+#[spacetimedb(reducer)]
+pub fn upsert_user_profile(ctx: ReducerContext, timestamp: Timestamp, display_name: String, avatar_url: Option<String>) -> Result<(), String> {
+    info!("Reducer: upsert_user_profile called by {:?} with display_name: {}", ctx.sender, display_name);
+    match UserProfile::filter_by_identity(&ctx.sender) {
+        Some(mut profile) => {
+            profile.display_name = display_name;
+            profile.avatar_url = avatar_url;
+            profile.updated_at = timestamp;
+            UserProfile::update_by_identity(&ctx.sender, profile);
+        }
+        None => {
+            UserProfile::insert(UserProfile {
+                identity: ctx.sender,
+                display_name,
+                avatar_url,
+                created_at: timestamp,
+                updated_at: timestamp,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates a new Workspace owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn create_workspace(ctx: ReducerContext, timestamp: Timestamp, name: String) -> Result<(), String> {
+    info!("Reducer: create_workspace called by {:?} with name: {}", ctx.sender, name);
+    Workspace::insert(Workspace {
+        id: 0, // ID is auto-generated
+        name,
+        created_at: timestamp,
+        owner_id: ctx.sender,
+    })?;
+    Ok(())
+}
+
+/// Deletes a Workspace owned by the calling identity. The Workspace must be empty: its
+/// Resources, Cards and Outputs have to be deleted first.
+#[spacetimedb(reducer)]
+pub fn delete_workspace(ctx: ReducerContext, id: u64) -> Result<(), String> {
+    info!("Reducer: delete_workspace called by {:?} for workspace: {}", ctx.sender, id);
+    owned_workspace(&ctx, id)?;
+
+    let rows = Resource::filter_by_workspace_id(&id).count()
+        + Card::filter_by_workspace_id(&id).count()
+        + Output::filter_by_workspace_id(&id).count();
+    if rows > 0 {
+        return Err(format!("Workspace {} still contains {} resources, cards or outputs", id, rows));
+    }
+
+    let members: Vec<u64> = WorkspaceMember::filter_by_workspace_id(&id).map(|member| member.id).collect();
+    for member_id in members {
+        WorkspaceMember::delete_by_id(&member_id);
+    }
+    Workspace::delete_by_id(&id);
+    Ok(())
+}
+
+/// Adds an identity to a Workspace owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn invite_workspace_member(ctx: ReducerContext, timestamp: Timestamp, workspace_id: u64, member_id: Identity, role: String) -> Result<(), String> {
+    info!("Reducer: invite_workspace_member called by {:?} for workspace: {}, member: {:?}, role: {}", ctx.sender, workspace_id, member_id, role);
+    let workspace = owned_workspace(&ctx, workspace_id)?;
+    if !WORKSPACE_ROLES.contains(&role.as_str()) {
+        return Err(format!("Unknown role '{}', expected one of: {}", role, WORKSPACE_ROLES.join(", ")));
+    }
+    if member_id == workspace.owner_id {
+        return Err(format!("Identity {:?} already owns workspace {}", member_id, workspace_id));
+    }
+    if find_workspace_member(workspace_id, &member_id).is_some() {
+        return Err(format!("Identity {:?} is already a member of workspace {}", member_id, workspace_id));
+    }
+
+    WorkspaceMember::insert(WorkspaceMember {
+        id: 0, // ID is auto-generated
+        workspace_id,
+        member_id,
+        role,
+        joined_at: timestamp,
+    })?;
+    Ok(())
+}
+
+/// Removes the calling identity from a Workspace it is a member of. Owners cannot leave their
+/// own Workspace; they delete it instead. Members must first delete or transfer the Resources,
+/// Cards and Outputs they own there, since nobody could remove them afterwards.
+#[spacetimedb(reducer)]
+pub fn leave_workspace(ctx: ReducerContext, workspace_id: u64) -> Result<(), String> {
+    info!("Reducer: leave_workspace called by {:?} for workspace: {}", ctx.sender, workspace_id);
+    let member = find_workspace_member(workspace_id, &ctx.sender)
+        .ok_or_else(|| format!("Caller is not a member of workspace {}", workspace_id))?;
+
+    let rows = Resource::filter_by_workspace_id(&workspace_id).filter(|resource| resource.owner_id == ctx.sender).count()
+        + Card::filter_by_workspace_id(&workspace_id).filter(|card| card.owner_id == ctx.sender).count()
+        + Output::filter_by_workspace_id(&workspace_id).filter(|output| output.owner_id == ctx.sender).count();
+    if rows > 0 {
+        return Err(format!("Caller still owns {} resources, cards or outputs in workspace {}", rows, workspace_id));
+    }
+    WorkspaceMember::delete_by_id(&member.id);
+    Ok(())
+}
+
+/// Creates a new Resource in a Workspace the calling identity owns or is an editor of.
 #[spacetimedb(reducer)]
-pub fn create_resource(ctx: ReducerContext, timestamp: Timestamp, name: String, resource_type: String) -> Result<(), String> {
-    info!("Reducer: create_resource called by {:?} with name: {}, type: {}", ctx.sender, name, resource_type);
+pub fn create_resource(ctx: ReducerContext, timestamp: Timestamp, workspace_id: u64, name: String, resource_type: ResourceType) -> Result<(), String> {
+    info!("Reducer: create_resource called by {:?} in workspace: {} with name: {}, type: {:?}", ctx.sender, workspace_id, name, resource_type);
+    writable_workspace(&ctx, workspace_id)?;
     Resource::insert(Resource {
         id: 0, // ID is auto-generated because of #[autoinc]
         name,
         resource_type,
         ingested_at: timestamp,
+        updated_at: timestamp,
         owner_id: ctx.sender,
+        workspace_id,
     })?;
     Ok(())
 }
 
+/// Updates the name and/or type of a Resource owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn update_resource(ctx: ReducerContext, timestamp: Timestamp, id: u64, name: Option<String>, resource_type: Option<ResourceType>) -> Result<(), String> {
+    info!("Reducer: update_resource called by {:?} for resource: {}", ctx.sender, id);
+    let mut resource = owned_resource(&ctx, id)?;
+    if let Some(name) = name {
+        resource.name = name;
+    }
+    if let Some(resource_type) = resource_type {
+        resource.resource_type = resource_type;
+    }
+    resource.updated_at = timestamp;
+    Resource::update_by_id(&id, resource);
+    Ok(())
+}
+
+/// Transfers a Resource owned by the calling identity to `new_owner`, who must be the owner
+/// or an editor of the Resource's Workspace. Resources still linked to Cards are refused, since
+/// the new owner could not unlink the previous owner's Cards.
+#[spacetimedb(reducer)]
+pub fn transfer_resource_ownership(ctx: ReducerContext, timestamp: Timestamp, resource_id: u64, new_owner: Identity) -> Result<(), String> {
+    info!("Reducer: transfer_resource_ownership called by {:?} for resource: {}, new owner: {:?}", ctx.sender, resource_id, new_owner);
+    let mut resource = owned_resource(&ctx, resource_id)?;
+    let workspace = Workspace::filter_by_id(&resource.workspace_id).ok_or_else(|| format!("Workspace {} not found", resource.workspace_id))?;
+    if !can_write_workspace(&workspace, &new_owner) {
+        return Err(format!("New owner is not an editor of workspace {}", workspace.id));
+    }
+    let links = CardResource::filter_by_resource_id(&resource_id).count();
+    if links > 0 {
+        return Err(format!("Resource {} still has {} card links", resource_id, links));
+    }
+    resource.owner_id = new_owner;
+    resource.updated_at = timestamp;
+    Resource::update_by_id(&resource_id, resource);
+    Ok(())
+}
+
+/// Deletes a Resource owned by the calling identity.
+///
+/// Outputs owned by the caller and generated from Cards linked to this Resource are either
+/// detached from their source card or, when `cascade` is true, deleted outright. Other
+/// identities' Outputs are left alone. CardResource links are removed.
+#[spacetimedb(reducer)]
+pub fn delete_resource(ctx: ReducerContext, id: u64, cascade: bool) -> Result<ResourceDeleteResult, String> {
+    info!("Reducer: delete_resource called by {:?} for resource: {}, cascade: {}", ctx.sender, id, cascade);
+    owned_resource(&ctx, id)?;
+
+    let links: Vec<CardResource> = CardResource::filter_by_resource_id(&id).collect();
+    let linked_cards: Vec<u64> = links.iter().map(|link| link.card_id).collect();
+
+    let mut result = ResourceDeleteResult {
+        resource_deleted: false,
+        outputs_detached: 0,
+        outputs_deleted: 0,
+        card_links_removed: 0,
+    };
+
+    let affected_outputs: Vec<Output> = Output::iter()
+        .filter(|output| output.owner_id == ctx.sender)
+        .filter(|output| output.source_card_id.is_some_and(|card_id| linked_cards.contains(&card_id)))
+        .collect();
+    for mut output in affected_outputs {
+        let output_id = output.id;
+        if cascade {
+            Output::delete_by_id(&output_id);
+            OutputContent::delete_by_output_id(&output_id);
+            result.outputs_deleted += 1;
+        } else {
+            output.source_card_id = None;
+            Output::update_by_id(&output_id, output);
+            result.outputs_detached += 1;
+        }
+    }
+
+    for link in links {
+        CardResource::delete_by_id(&link.id);
+        result.card_links_removed += 1;
+    }
+
+    let tags: Vec<u64> = ResourceTag::filter_by_resource_id(&id).map(|tag| tag.id).collect();
+    for tag_id in tags {
+        ResourceTag::delete_by_id(&tag_id);
+    }
+
+    result.resource_deleted = Resource::delete_by_id(&id);
+    Ok(result)
+}
+
+/// Returns every Resource of the given type in Workspaces the calling identity owns or is a
+/// member of.
+#[spacetimedb(reducer)]
+pub fn find_resources_by_type(ctx: ReducerContext, resource_type: ResourceType) -> Vec<Resource> {
+    info!("Reducer: find_resources_by_type called by {:?} with type: {:?}", ctx.sender, resource_type);
+    Resource::filter_by_resource_type(&resource_type)
+        .filter(|resource| accessible_workspace(&ctx, resource.workspace_id).is_ok())
+        .collect()
+}
+
+/// Tags a Resource owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn tag_resource(ctx: ReducerContext, timestamp: Timestamp, resource_id: u64, tag: String) -> Result<(), String> {
+    info!("Reducer: tag_resource called by {:?} for resource: {}, tag: {}", ctx.sender, resource_id, tag);
+    owned_resource(&ctx, resource_id)?;
+
+    if find_resource_tag(resource_id, &tag).is_some() {
+        return Err(format!("Resource {} is already tagged '{}'", resource_id, tag));
+    }
+
+    ResourceTag::insert(ResourceTag {
+        id: 0, // ID is auto-generated
+        resource_id,
+        tag,
+        tagged_by: ctx.sender,
+        tagged_at: timestamp,
+    })?;
+    Ok(())
+}
+
+/// Removes a tag from a Resource owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn untag_resource(ctx: ReducerContext, resource_id: u64, tag: String) -> Result<(), String> {
+    info!("Reducer: untag_resource called by {:?} for resource: {}, tag: {}", ctx.sender, resource_id, tag);
+    owned_resource(&ctx, resource_id)?;
+
+    let resource_tag = find_resource_tag(resource_id, &tag)
+        .ok_or_else(|| format!("Resource {} is not tagged '{}'", resource_id, tag))?;
+    ResourceTag::delete_by_id(&resource_tag.id);
+    Ok(())
+}
+
+/// Returns every Resource with the given tag in Workspaces the calling identity can access.
+/// Combine with `resource_type` on the client for queries like "GitHub resources tagged auth".
+#[spacetimedb(reducer)]
+pub fn find_resources_by_tag(ctx: ReducerContext, tag: String) -> Vec<Resource> {
+    info!("Reducer: find_resources_by_tag called by {:?} with tag: {}", ctx.sender, tag);
+    ResourceTag::filter_by_tag(&tag)
+        .filter_map(|resource_tag| Resource::filter_by_id(&resource_tag.resource_id))
+        .filter(|resource| accessible_workspace(&ctx, resource.workspace_id).is_ok())
+        .collect()
+}
+
 /// Example reducer - kept for reference, consider removing in final template
 #[spacetimedb(reducer)]
-pub fn create_dummy_resource(ctx: ReducerContext, timestamp: Timestamp, name: String, resource_type: String) -> Result<(), String> {
-    info!("Creating dummy resource: {} ({})", name, resource_type);
+pub fn create_dummy_resource(ctx: ReducerContext, timestamp: Timestamp, workspace_id: u64, name: String, resource_type: ResourceType) -> Result<(), String> {
+    info!("Creating dummy resource: {} ({:?})", name, resource_type);
+    writable_workspace(&ctx, workspace_id)?;
     Resource::insert(Resource {
         id: 0, // ID is auto-generated
         name,
         resource_type,
         ingested_at: timestamp,
+        updated_at: timestamp,
         owner_id: ctx.sender,
+        workspace_id,
     })?;
     Ok(())
 }
 
+/// Creates a new Card in a Workspace the calling identity owns or is an editor of.
+#[spacetimedb(reducer)]
+pub fn create_card(ctx: ReducerContext, timestamp: Timestamp, workspace_id: u64, title: String, content: String) -> Result<(), String> {
+    info!("Reducer: create_card called by {:?} in workspace: {} with title: {}", ctx.sender, workspace_id, title);
+    writable_workspace(&ctx, workspace_id)?;
+    let card = Card::insert(Card {
+        id: 0, // ID is auto-generated
+        title,
+        content,
+        created_at: timestamp,
+        updated_at: timestamp,
+        owner_id: ctx.sender,
+        workspace_id,
+    })?;
+    sync_card_text(&card);
+    Ok(())
+}
+
+/// Creates a new Card, owned by the calling identity, with the content of an existing Card in a
+/// Workspace the caller can edit. The copy stays in the source Card's Workspace and its id is
+/// returned.
+#[spacetimedb(reducer)]
+pub fn duplicate_card(ctx: ReducerContext, timestamp: Timestamp, source_card_id: u64, new_title: String) -> Result<u64, String> {
+    info!("Reducer: duplicate_card called by {:?} for card: {} with title: {}", ctx.sender, source_card_id, new_title);
+    let source = Card::filter_by_id(&source_card_id).ok_or_else(|| format!("Card {} not found", source_card_id))?;
+    writable_workspace(&ctx, source.workspace_id)?;
+
+    let card = Card::insert(Card {
+        id: 0, // ID is auto-generated
+        title: new_title,
+        content: source.content,
+        created_at: timestamp,
+        updated_at: timestamp,
+        owner_id: ctx.sender,
+        workspace_id: source.workspace_id,
+    })?;
+    sync_card_text(&card);
+    Ok(card.id)
+}
+
+/// Updates the title and/or content of a Card owned by, or shared for editing with, the calling
+/// identity.
+#[spacetimedb(reducer)]
+pub fn update_card(ctx: ReducerContext, timestamp: Timestamp, id: u64, title: Option<String>, content: Option<String>) -> Result<(), String> {
+    info!("Reducer: update_card called by {:?} for card: {}", ctx.sender, id);
+    let mut card = editable_card(&ctx, id)?;
+    CardVersion::insert(CardVersion {
+        version_id: 0, // ID is auto-generated
+        card_id: id,
+        title: card.title.clone(),
+        content: card.content.clone(),
+        snapshot_at: timestamp,
+        owner_id: card.owner_id,
+    })?;
+    if let Some(title) = title {
+        card.title = title;
+    }
+    if let Some(content) = content {
+        card.content = content;
+    }
+    card.updated_at = timestamp;
+    sync_card_text(&card);
+    Card::update_by_id(&id, card);
+    Ok(())
+}
+
+/// Lists the saved versions of a Card owned by the calling identity, oldest first.
+#[spacetimedb(reducer)]
+pub fn list_card_versions(ctx: ReducerContext, card_id: u64, page: PageRequest) -> Page<CardVersion> {
+    info!("Reducer: list_card_versions called by {:?} for card: {}", ctx.sender, card_id);
+    let mut versions: Vec<CardVersion> = CardVersion::filter_by_card_id(&card_id)
+        .filter(|version| version.owner_id == ctx.sender)
+        .collect();
+    versions.sort_by_key(|version| version.version_id);
+    paginate(versions, &page)
+}
+
+/// Moves a Card owned by the calling identity to another Workspace the caller owns.
+/// Cards that are still linked to Resources, other Cards or Outputs are refused, since those
+/// links would then cross Workspaces.
+#[spacetimedb(reducer)]
+pub fn move_card_to_workspace(ctx: ReducerContext, timestamp: Timestamp, card_id: u64, new_workspace_id: u64) -> Result<(), String> {
+    info!("Reducer: move_card_to_workspace called by {:?} for card: {}, workspace: {}", ctx.sender, card_id, new_workspace_id);
+    let mut card = owned_card(&ctx, card_id)?;
+    owned_workspace(&ctx, new_workspace_id)?;
+    if card.workspace_id == new_workspace_id {
+        return Ok(());
+    }
+
+    let links = CardResource::filter_by_card_id(&card_id).count()
+        + CardLink::iter().filter(|link| link.source_card_id == card_id || link.target_card_id == card_id).count()
+        + Output::iter().filter(|output| output.source_card_id == Some(card_id)).count();
+    if links > 0 {
+        return Err(format!("Card {} still has {} links to rows in workspace {}", card_id, links, card.workspace_id));
+    }
+
+    card.workspace_id = new_workspace_id;
+    card.updated_at = timestamp;
+    Card::update_by_id(&card_id, card);
+    Ok(())
+}
+
+/// Shares a Card owned by the calling identity with another identity, or changes `can_edit` on
+/// an existing share.
+#[spacetimedb(reducer)]
+pub fn share_card(ctx: ReducerContext, timestamp: Timestamp, card_id: u64, target: Identity, can_edit: bool) -> Result<(), String> {
+    info!("Reducer: share_card called by {:?} for card: {} with {:?}, can_edit: {}", ctx.sender, card_id, target, can_edit);
+    let card = owned_card(&ctx, card_id)?;
+    if target == card.owner_id {
+        return Err(format!("Card {} cannot be shared with its owner", card_id));
+    }
+
+    match find_card_share(card_id, &target) {
+        Some(mut share) => {
+            let share_id = share.id;
+            share.can_edit = can_edit;
+            SharedCard::update_by_id(&share_id, share);
+        }
+        None => {
+            SharedCard::insert(SharedCard {
+                id: 0, // ID is auto-generated
+                card_id,
+                shared_with: target,
+                can_edit,
+                shared_at: timestamp,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Revokes a share of a Card owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn revoke_card_share(ctx: ReducerContext, card_id: u64, target: Identity) -> Result<(), String> {
+    info!("Reducer: revoke_card_share called by {:?} for card: {} with {:?}", ctx.sender, card_id, target);
+    owned_card(&ctx, card_id)?;
+
+    let share = find_card_share(card_id, &target)
+        .ok_or_else(|| format!("Card {} is not shared with {:?}", card_id, target))?;
+    SharedCard::delete_by_id(&share.id);
+    Ok(())
+}
+
+/// Deletes a Card owned by the calling identity, along with every link that references it.
+/// Outputs generated from the Card are kept but detached from it.
+#[spacetimedb(reducer)]
+pub fn delete_card(ctx: ReducerContext, id: u64) -> Result<DeleteResult, String> {
+    info!("Reducer: delete_card called by {:?} for card: {}", ctx.sender, id);
+    owned_card(&ctx, id)?;
+
+    let card_links: Vec<u64> = CardLink::iter()
+        .filter(|link| link.source_card_id == id || link.target_card_id == id)
+        .map(|link| link.id)
+        .collect();
+    let resource_links: Vec<u64> = CardResource::filter_by_card_id(&id).map(|link| link.id).collect();
+    let tags: Vec<u64> = CardTag::filter_by_card_id(&id).map(|tag| tag.id).collect();
+
+    let mut links_removed = 0;
+    for link_id in card_links {
+        CardLink::delete_by_id(&link_id);
+        links_removed += 1;
+    }
+    for link_id in resource_links {
+        CardResource::delete_by_id(&link_id);
+        links_removed += 1;
+    }
+    for tag_id in tags {
+        CardTag::delete_by_id(&tag_id);
+    }
+    let shares: Vec<u64> = SharedCard::filter_by_card_id(&id).map(|share| share.id).collect();
+    for share_id in shares {
+        SharedCard::delete_by_id(&share_id);
+    }
+    remove_card_text(id);
+
+    let outputs: Vec<Output> = Output::iter().filter(|output| output.source_card_id == Some(id)).collect();
+    let mut outputs_detached = 0;
+    for mut output in outputs {
+        let output_id = output.id;
+        output.source_card_id = None;
+        Output::update_by_id(&output_id, output);
+        outputs_detached += 1;
+    }
+
+    let card_deleted = Card::delete_by_id(&id);
+    Ok(DeleteResult { card_deleted, links_removed, outputs_detached })
+}
+
+/// Tags a Card owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn tag_card(ctx: ReducerContext, timestamp: Timestamp, card_id: u64, tag: String) -> Result<(), String> {
+    info!("Reducer: tag_card called by {:?} for card: {}, tag: {}", ctx.sender, card_id, tag);
+    owned_card(&ctx, card_id)?;
+
+    if find_card_tag(card_id, &tag).is_some() {
+        return Err(format!("Card {} is already tagged '{}'", card_id, tag));
+    }
+
+    CardTag::insert(CardTag {
+        id: 0, // ID is auto-generated
+        card_id,
+        tag,
+        tagged_by: ctx.sender,
+        tagged_at: timestamp,
+    })?;
+    Ok(())
+}
+
+/// Removes a tag from a Card owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn untag_card(ctx: ReducerContext, card_id: u64, tag: String) -> Result<(), String> {
+    info!("Reducer: untag_card called by {:?} for card: {}, tag: {}", ctx.sender, card_id, tag);
+    owned_card(&ctx, card_id)?;
+
+    let card_tag = find_card_tag(card_id, &tag)
+        .ok_or_else(|| format!("Card {} is not tagged '{}'", card_id, tag))?;
+    CardTag::delete_by_id(&card_tag.id);
+    Ok(())
+}
+
+/// Returns every Card with the given tag that the calling identity can see.
+#[spacetimedb(reducer)]
+pub fn find_cards_by_tag(ctx: ReducerContext, tag: String) -> Vec<Card> {
+    info!("Reducer: find_cards_by_tag called by {:?} with tag: {}", ctx.sender, tag);
+    CardTag::filter_by_tag(&tag)
+        .filter_map(|card_tag| Card::filter_by_id(&card_tag.card_id))
+        .filter(|card| can_view_card(&ctx, card))
+        .collect()
+}
+
+/// Returns up to `limit` Cards whose title or content contains `query` (case-insensitive),
+/// most recently updated first. Only Cards the calling identity can see are returned. Queries
+/// shorter than three characters match nothing.
+#[spacetimedb(reducer)]
+pub fn search_cards(ctx: ReducerContext, query: String, limit: u32) -> Vec<Card> {
+    info!("Reducer: search_cards called by {:?} with query: {}", ctx.sender, query);
+    let mut grams = trigrams(&query).into_iter();
+    let mut candidates: HashSet<u64> = match grams.next() {
+        Some(first) => card_ids_with_trigram(&first),
+        None => return Vec::new(),
+    };
+    for gram in grams {
+        if candidates.is_empty() {
+            break;
+        }
+        let ids = card_ids_with_trigram(&gram);
+        candidates.retain(|id| ids.contains(id));
+    }
+
+    let mut cards: Vec<Card> = candidates
+        .into_iter()
+        .filter_map(|id| Card::filter_by_id(&id))
+        .filter(|card| can_view_card(&ctx, card))
+        .collect();
+    cards.sort_by_key(|card| Reverse(card.updated_at));
+    cards.truncate(limit as usize);
+    cards
+}
+
+/// Creates a new Output in a Workspace the calling identity owns or is an editor of, optionally
+/// generated from a Card in the same Workspace.
+#[spacetimedb(reducer)]
+pub fn create_output(ctx: ReducerContext, timestamp: Timestamp, workspace_id: u64, name: String, format: OutputFormat, source_card_id: Option<u64>) -> Result<(), String> {
+    info!("Reducer: create_output called by {:?} in workspace: {} with name: {}, format: {:?}", ctx.sender, workspace_id, name, format);
+    writable_workspace(&ctx, workspace_id)?;
+    if let Some(card_id) = source_card_id {
+        let card = owned_card(&ctx, card_id)?;
+        same_workspace(workspace_id, card.workspace_id)?;
+    }
+
+    Output::insert(Output {
+        id: 0, // ID is auto-generated
+        name,
+        format,
+        created_at: timestamp,
+        updated_at: timestamp,
+        source_card_id,
+        owner_id: ctx.sender,
+        workspace_id,
+    })?;
+    Ok(())
+}
+
+/// Updates the name and/or format of an Output owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn update_output(ctx: ReducerContext, timestamp: Timestamp, id: u64, name: Option<String>, format: Option<OutputFormat>) -> Result<(), String> {
+    info!("Reducer: update_output called by {:?} for output: {}", ctx.sender, id);
+    let mut output = owned_output(&ctx, id)?;
+    if let Some(name) = name {
+        output.name = name;
+    }
+    if let Some(format) = format {
+        output.format = format;
+    }
+    output.updated_at = timestamp;
+    Output::update_by_id(&id, output);
+    Ok(())
+}
+
+/// Deletes an Output owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn delete_output(ctx: ReducerContext, id: u64) -> Result<(), String> {
+    info!("Reducer: delete_output called by {:?} for output: {}", ctx.sender, id);
+    owned_output(&ctx, id)?;
+    Output::delete_by_id(&id);
+    OutputContent::delete_by_output_id(&id);
+    Ok(())
+}
+
+/// Stores (or replaces) the payload of an Output owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn store_output_content(ctx: ReducerContext, timestamp: Timestamp, output_id: u64, content_bytes: Vec<u8>) -> Result<(), String> {
+    info!("Reducer: store_output_content called by {:?} for output: {}, bytes: {}", ctx.sender, output_id, content_bytes.len());
+    owned_output(&ctx, output_id)?;
+    let content = OutputContent {
+        output_id,
+        content_bytes,
+        stored_at: timestamp,
+    };
+    if OutputContent::filter_by_output_id(&output_id).is_some() {
+        OutputContent::update_by_output_id(&output_id, content);
+    } else {
+        OutputContent::insert(content)?;
+    }
+    Ok(())
+}
+
+/// Links a Card to a Resource. Both must belong to the calling identity and share a Workspace.
+#[spacetimedb(reducer)]
+pub fn link_card_resource(ctx: ReducerContext, timestamp: Timestamp, card_id: u64, resource_id: u64) -> Result<(), String> {
+    info!("Reducer: link_card_resource called by {:?} for card: {}, resource: {}", ctx.sender, card_id, resource_id);
+    let card = owned_card(&ctx, card_id)?;
+    let resource = owned_resource(&ctx, resource_id)?;
+    same_workspace(card.workspace_id, resource.workspace_id)?;
+
+    if find_card_resource(card_id, resource_id).is_some() {
+        return Err(format!("Card {} is already linked to resource {}", card_id, resource_id));
+    }
+
+    CardResource::insert(CardResource {
+        id: 0, // ID is auto-generated
+        card_id,
+        resource_id,
+        linked_at: timestamp,
+        owner_id: ctx.sender,
+    })?;
+    Ok(())
+}
+
+/// Removes the link between a Card and a Resource.
+#[spacetimedb(reducer)]
+pub fn unlink_card_resource(ctx: ReducerContext, card_id: u64, resource_id: u64) -> Result<(), String> {
+    info!("Reducer: unlink_card_resource called by {:?} for card: {}, resource: {}", ctx.sender, card_id, resource_id);
+    owned_card(&ctx, card_id)?;
+    owned_resource(&ctx, resource_id)?;
+
+    let link = find_card_resource(card_id, resource_id)
+        .ok_or_else(|| format!("Card {} is not linked to resource {}", card_id, resource_id))?;
+    CardResource::delete_by_id(&link.id);
+    Ok(())
+}
+
+/// Creates a typed link between two Cards owned by the calling identity in the same Workspace.
+#[spacetimedb(reducer)]
+pub fn create_card_link(ctx: ReducerContext, timestamp: Timestamp, source_card_id: u64, target_card_id: u64, link_type: String) -> Result<(), String> {
+    info!("Reducer: create_card_link called by {:?} from card: {} to card: {}, type: {}", ctx.sender, source_card_id, target_card_id, link_type);
+    if source_card_id == target_card_id {
+        return Err(format!("Card {} cannot be linked to itself", source_card_id));
+    }
+    let source = owned_card(&ctx, source_card_id)?;
+    let target = owned_card(&ctx, target_card_id)?;
+    same_workspace(source.workspace_id, target.workspace_id)?;
+
+    CardLink::insert(CardLink {
+        id: 0, // ID is auto-generated
+        source_card_id,
+        target_card_id,
+        link_type,
+        created_at: timestamp,
+        owner_id: ctx.sender,
+    })?;
+    Ok(())
+}
+
+/// Deletes a CardLink owned by the calling identity.
+#[spacetimedb(reducer)]
+pub fn delete_card_link(ctx: ReducerContext, id: u64) -> Result<(), String> {
+    info!("Reducer: delete_card_link called by {:?} for link: {}", ctx.sender, id);
+    let link = CardLink::filter_by_id(&id).ok_or_else(|| format!("Card link {} not found", id))?;
+    if link.owner_id != ctx.sender {
+        return Err(format!("Card link {} is not owned by the caller", id));
+    }
+    CardLink::delete_by_id(&id);
+    Ok(())
+}
+
+// --- Helpers ---
+
+/// Looks up a Workspace and checks that it belongs to the calling identity.
+fn owned_workspace(ctx: &ReducerContext, workspace_id: u64) -> Result<Workspace, String> {
+    let workspace = Workspace::filter_by_id(&workspace_id).ok_or_else(|| format!("Workspace {} not found", workspace_id))?;
+    if workspace.owner_id != ctx.sender {
+        return Err(format!("Workspace {} is not owned by the caller", workspace_id));
+    }
+    Ok(workspace)
+}
+
+/// Looks up a Workspace and checks that the calling identity owns it or is a member of it.
+fn accessible_workspace(ctx: &ReducerContext, workspace_id: u64) -> Result<Workspace, String> {
+    let workspace = Workspace::filter_by_id(&workspace_id).ok_or_else(|| format!("Workspace {} not found", workspace_id))?;
+    if workspace.owner_id != ctx.sender && find_workspace_member(workspace_id, &ctx.sender).is_none() {
+        return Err(format!("Caller is not a member of workspace {}", workspace_id));
+    }
+    Ok(workspace)
+}
+
+/// Looks up a Workspace and checks that the calling identity owns it or is an editor of it.
+fn writable_workspace(ctx: &ReducerContext, workspace_id: u64) -> Result<Workspace, String> {
+    let workspace = Workspace::filter_by_id(&workspace_id).ok_or_else(|| format!("Workspace {} not found", workspace_id))?;
+    if !can_write_workspace(&workspace, &ctx.sender) {
+        return Err(format!("Caller is not an editor of workspace {}", workspace_id));
+    }
+    Ok(workspace)
+}
+
+fn can_write_workspace(workspace: &Workspace, identity: &Identity) -> bool {
+    workspace.owner_id == *identity
+        || find_workspace_member(workspace.id, identity).is_some_and(|member| member.role == EDITOR_ROLE)
+}
+
+fn find_workspace_member(workspace_id: u64, member_id: &Identity) -> Option<WorkspaceMember> {
+    WorkspaceMember::filter_by_workspace_id(&workspace_id).find(|member| member.member_id == *member_id)
+}
+
+/// Rejects operations that would connect rows from different Workspaces.
+fn same_workspace(a: u64, b: u64) -> Result<(), String> {
+    if a != b {
+        return Err(format!("Workspace {} does not match workspace {}", a, b));
+    }
+    Ok(())
+}
+
+/// Looks up a Card and checks that it belongs to the calling identity and a Workspace it can edit.
+fn owned_card(ctx: &ReducerContext, card_id: u64) -> Result<Card, String> {
+    let card = Card::filter_by_id(&card_id).ok_or_else(|| format!("Card {} not found", card_id))?;
+    if card.owner_id != ctx.sender {
+        return Err(format!("Card {} is not owned by the caller", card_id));
+    }
+    writable_workspace(ctx, card.workspace_id)?;
+    Ok(card)
+}
+
+/// Looks up a Card and checks that the calling identity owns it or has an editable share of it.
+fn editable_card(ctx: &ReducerContext, card_id: u64) -> Result<Card, String> {
+    let card = Card::filter_by_id(&card_id).ok_or_else(|| format!("Card {} not found", card_id))?;
+    if card.owner_id == ctx.sender {
+        return owned_card(ctx, card_id);
+    }
+    match find_card_share(card_id, &ctx.sender) {
+        Some(share) if share.can_edit => Ok(card),
+        _ => Err(format!("Card {} is not owned by or shared for editing with the caller", card_id)),
+    }
+}
+
+/// Whether the calling identity can see a Card, through its Workspace or a share.
+fn can_view_card(ctx: &ReducerContext, card: &Card) -> bool {
+    accessible_workspace(ctx, card.workspace_id).is_ok() || find_card_share(card.id, &ctx.sender).is_some()
+}
+
+fn find_card_share(card_id: u64, identity: &Identity) -> Option<SharedCard> {
+    SharedCard::filter_by_card_id(&card_id).find(|share| share.shared_with == *identity)
+}
+
+/// Looks up a Resource and checks that it belongs to the calling identity and a Workspace it can edit.
+fn owned_resource(ctx: &ReducerContext, resource_id: u64) -> Result<Resource, String> {
+    let resource = Resource::filter_by_id(&resource_id).ok_or_else(|| format!("Resource {} not found", resource_id))?;
+    if resource.owner_id != ctx.sender {
+        return Err(format!("Resource {} is not owned by the caller", resource_id));
+    }
+    writable_workspace(ctx, resource.workspace_id)?;
+    Ok(resource)
+}
+
+/// Looks up an Output and checks that it belongs to the calling identity and a Workspace it can edit.
+fn owned_output(ctx: &ReducerContext, output_id: u64) -> Result<Output, String> {
+    let output = Output::filter_by_id(&output_id).ok_or_else(|| format!("Output {} not found", output_id))?;
+    if output.owner_id != ctx.sender {
+        return Err(format!("Output {} is not owned by the caller", output_id));
+    }
+    writable_workspace(ctx, output.workspace_id)?;
+    Ok(output)
+}
+
+fn paginate<T>(items: Vec<T>, page: &PageRequest) -> Page<T> {
+    let total = items.len() as u64;
+    let has_more = page.offset.saturating_add(page.limit) < total;
+    let items = items.into_iter().skip(page.offset as usize).take(page.limit as usize).collect();
+    Page { items, total, has_more }
+}
+
+fn find_card_tag(card_id: u64, tag: &str) -> Option<CardTag> {
+    CardTag::filter_by_card_id(&card_id).find(|card_tag| card_tag.tag == tag)
+}
+
+fn find_resource_tag(resource_id: u64, tag: &str) -> Option<ResourceTag> {
+    ResourceTag::filter_by_resource_id(&resource_id).find(|resource_tag| resource_tag.tag == tag)
+}
+
+/// Rebuilds the CardTextIndex rows of a Card. Title and content are indexed separately so no
+/// trigram spans the boundary between them.
+fn sync_card_text(card: &Card) {
+    remove_card_text(card.id);
+    let mut grams = trigrams(&card.title);
+    grams.extend(trigrams(&card.content));
+    grams.sort();
+    grams.dedup();
+    for trigram in grams {
+        if let Err(e) = CardTextIndex::insert(CardTextIndex { id: 0, trigram, card_id: card.id }) {
+            warn!("Failed to index card {}: {:?}", card.id, e);
+        }
+    }
+}
+
+fn remove_card_text(card_id: u64) {
+    let stale: Vec<u64> = CardTextIndex::filter_by_card_id(&card_id).map(|row| row.id).collect();
+    for id in stale {
+        CardTextIndex::delete_by_id(&id);
+    }
+}
+
+fn card_ids_with_trigram(trigram: &str) -> HashSet<u64> {
+    CardTextIndex::filter_by_trigram(&trigram.to_string()).map(|row| row.card_id).collect()
+}
+
+/// Distinct lowercase character 3-grams of `text`.
+fn trigrams(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    let mut grams: Vec<String> = chars.windows(3).map(|w| w.iter().collect()).collect();
+    grams.sort();
+    grams.dedup();
+    grams
+}
+
+fn find_card_resource(card_id: u64, resource_id: u64) -> Option<CardResource> {
+    CardResource::filter_by_card_id(&card_id).find(|link| link.resource_id == resource_id)
+}
+
 // Note: Read operations typically don't need reducers.
 // Clients subscribe to tables (e.g., `SELECT * FROM Resource`)
 // and SpacetimeDB pushes updates automatically.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginate_returns_requested_window() {
+        let page: Page<i32> = paginate((1..=5).collect(), &PageRequest { offset: 1, limit: 2 });
+        assert_eq!(page.items, vec![2, 3]);
+        assert_eq!(page.total, 5);
+        assert!(page.has_more);
+    }
+
+    #[test]
+    fn paginate_last_page_has_no_more() {
+        let page: Page<i32> = paginate((1..=5).collect(), &PageRequest { offset: 4, limit: 10 });
+        assert_eq!(page.items, vec![5]);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn trigrams_are_lowercase_sorted_and_distinct() {
+        assert_eq!(trigrams("Abcabc"), vec!["abc", "bca", "cab"]);
+        assert!(trigrams("ab").is_empty());
+    }
+}